@@ -0,0 +1,181 @@
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+use tracing::info;
+use crate::cover_path::cover_path;
+use crate::Args;
+
+/// An image of a cover, stored at the same place as the web app's CoverStore puts it
+struct Key {
+    series: String,
+    number: i32,
+    /// (size, format)
+    variant: Option<(String, String)>,
+}
+
+impl Key {
+    /// "PR/12/original", "PR/12/thumb.webp"
+    fn path(&self) -> String {
+        cover_path(&self.series, self.number, self.variant.as_ref().map(|(size, format)| (size.as_str(), format.as_str())))
+    }
+}
+
+/// Where the images of the covers can be: "postgres", "filesystem" or "s3"
+enum Store {
+    Postgres(PgPool),
+    Object(Box<dyn ObjectStore>),
+}
+
+impl Store {
+    fn create(name: &str, args: &Args, pool: &PgPool) -> Result<Store, String> {
+        let config = &args.config;
+        match name {
+            "postgres" => { Ok(Store::Postgres(pool.clone())) }
+            "filesystem" => {
+                let dir = config.cover_dir.clone().unwrap_or("covers".into());
+                std::fs::create_dir_all(&dir).map_err(|e| format!("{dir}: {e}"))?;
+                let store = LocalFileSystem::new_with_prefix(&dir).map_err(|e| e.to_string())?;
+                Ok(Store::Object(Box::new(store)))
+            }
+            "s3" => {
+                let Some(s3) = &config.s3 else { return Err("db.toml has no [s3] section".into()) };
+                let mut builder = AmazonS3Builder::new()
+                    .with_bucket_name(&s3.bucket)
+                    .with_region(s3.region.clone().unwrap_or("us-east-1".into()))
+                    .with_access_key_id(&s3.access_key_id)
+                    .with_secret_access_key(&s3.secret_access_key);
+                if let Some(endpoint) = &s3.endpoint {
+                    builder = builder.with_endpoint(endpoint)
+                        .with_virtual_hosted_style_request(false)
+                        .with_allow_http(endpoint.starts_with("http://"));
+                }
+                Ok(Store::Object(Box::new(builder.build().map_err(|e| e.to_string())?)))
+            }
+            _ => { Err(format!("Unknown cover store {name}, expected postgres, filesystem or s3")) }
+        }
+    }
+
+    async fn get(&self, key: &Key) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            Store::Postgres(pool) => {
+                let query = match &key.variant {
+                    None => {
+                        sqlx::query("select image from covers where series = $1 and number = $2")
+                            .bind(&key.series).bind(key.number)
+                    }
+                    Some((size, format)) => {
+                        sqlx::query("select image from cover_variants where series = $1 and number = $2 \
+                                and size = $3 and format = $4")
+                            .bind(&key.series).bind(key.number).bind(size).bind(format)
+                    }
+                };
+                let row = query.fetch_one(pool).await.map_err(|e| e.to_string())?;
+                row.try_get::<Option<Vec<u8>>, _>("image").map_err(|e| e.to_string())
+            }
+            Store::Object(store) => {
+                match store.get(&Path::from(key.path())).await {
+                    Ok(result) => { Ok(Some(result.bytes().await.map_err(|e| e.to_string())?.to_vec())) }
+                    Err(object_store::Error::NotFound { .. }) => { Ok(None) }
+                    Err(e) => { Err(e.to_string()) }
+                }
+            }
+        }
+    }
+
+    /// `None` clears the image, once it's been copied to the other store
+    async fn put(&self, key: &Key, image: Option<Vec<u8>>) -> Result<(), String> {
+        match self {
+            Store::Postgres(pool) => {
+                let query = match &key.variant {
+                    None => {
+                        sqlx::query("update covers set image = $3 where series = $1 and number = $2")
+                            .bind(&key.series).bind(key.number).bind(image)
+                    }
+                    Some((size, format)) => {
+                        sqlx::query("update cover_variants set image = $3 where series = $1 and number = $2 \
+                                and size = $4 and format = $5")
+                            .bind(&key.series).bind(key.number).bind(image).bind(size).bind(format)
+                    }
+                };
+                query.execute(pool).await.map(|_| ()).map_err(|e| e.to_string())
+            }
+            Store::Object(store) => {
+                let path = Path::from(key.path());
+                match image {
+                    Some(bytes) => {
+                        store.put(&path, PutPayload::from(bytes)).await.map(|_| ()).map_err(|e| e.to_string())
+                    }
+                    None => {
+                        match store.delete(&path).await {
+                            Ok(_) | Err(object_store::Error::NotFound { .. }) => { Ok(()) }
+                            Err(e) => { Err(e.to_string()) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Move the images of all the covers and their variants from one store to another. The web
+/// app must then be restarted with COVER_STORE set to the new store.
+pub async fn move_covers(args: &Args, from: &str, to: &str) -> Result<(), String> {
+    if from == to {
+        return Err(format!("The covers are already in {from}"));
+    }
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&args.config.local_url).await
+        .map_err(|e| e.to_string())?;
+    let source = Store::create(from, args, &pool)?;
+    let target = Store::create(to, args, &pool)?;
+
+    let mut keys: Vec<Key> = Vec::new();
+    for row in sqlx::query("select series, number from covers order by series, number")
+        .fetch_all(&pool).await.map_err(|e| e.to_string())?
+    {
+        keys.push(Key { series: row.get("series"), number: row.get("number"), variant: None });
+    }
+    for row in sqlx::query("select series, number, size, format from cover_variants order by series, number")
+        .fetch_all(&pool).await.map_err(|e| e.to_string())?
+    {
+        keys.push(Key {
+            series: row.get("series"),
+            number: row.get("number"),
+            variant: Some((row.get("size"), row.get("format"))),
+        });
+    }
+
+    info!("Moving {} cover images from {from} to {to}", keys.len());
+    let (mut moved, mut missing) = (0, 0);
+    for key in keys {
+        match source.get(&key).await? {
+            Some(bytes) => {
+                let length = bytes.len();
+                target.put(&key, Some(bytes)).await?;
+                // Only forget the image once the target is known to have all of it
+                match target.get(&key).await? {
+                    Some(copy) if copy.len() == length => {}
+                    copy => {
+                        return Err(format!("The copy of {} in {to} has {} bytes instead of {length}, \
+                            it's still in {from}", key.path(), copy.map_or(0, |c| c.len())));
+                    }
+                }
+                source.put(&key, None).await?;
+                moved += 1;
+                if moved % 100 == 0 {
+                    info!("Moved {moved} images");
+                }
+            }
+            None => {
+                missing += 1;
+            }
+        }
+    }
+    info!("Moved {moved} images, {missing} were not in {from}");
+
+    Ok(())
+}
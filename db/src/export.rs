@@ -21,7 +21,7 @@ pub async fn get_summaries(args: &Args) -> Result<Vec<Summary>, sqlx::Error> {
         .connect(&url).await?;
 
     let mut result: Vec<Summary> =
-        sqlx::query_as("select number, english_title, summary from summaries order by number")
+        sqlx::query_as("select number, english_title, summary from summaries where series = 'PR' order by number")
         .fetch_all(&pool)
         .await?;
 
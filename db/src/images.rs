@@ -25,7 +25,7 @@ pub async fn images(args: &Args) -> Result<(), sqlx::Error> {
         .connect(&url).await?;
 
     let original_covers = sqlx::query_as::<_, Cover>(
-        "select * from covers where number = 2991")
+        "select * from covers where series = 'PR' and number = 2991")
         .fetch_all(&pool)
         .await?;
 
@@ -47,11 +47,19 @@ pub async fn images(args: &Args) -> Result<(), sqlx::Error> {
 
     if true {
         for c in new_covers.read().unwrap().iter() {
+            // The hash identifies the new image, the report analyzes it again
             sqlx::query!(
-                "update covers set image = $2, size = $3 where number = $1", c.number, c.image,
+                "update covers set image = $2, size = $3, hash = encode(sha256($2), 'hex'), \
+                    width = null, height = null, phash = null \
+                    where series = 'PR' and number = $1", c.number, c.image,
                     c.image.len() as i32)
                 .execute(&pool)
                 .await?;
+            // The web app resizes the new image again the next time that cover is requested
+            sqlx::query("delete from cover_variants where series = 'PR' and number = $1")
+                .bind(c.number)
+                .execute(&pool)
+                .await?;
             info!("Update summary {}", c.number);
         }
     }
@@ -10,6 +10,7 @@ use tracing::{error, info};
 use tracing_subscriber::fmt;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use crate::covers::move_covers;
 use crate::images::images;
 use crate::import::run_import;
 use crate::export::{to_json, to_xml};
@@ -19,6 +20,10 @@ mod db;
 mod test;
 mod images;
 mod export;
+mod covers;
+// Shared with the web app
+#[path = "../../web/src/cover_path.rs"]
+mod cover_path;
 
 pub fn init_logging(sqlx: bool) {
     let debug_sqlx = if sqlx { "debug" } else { "info" };
@@ -78,6 +83,12 @@ pub async fn main() -> Result<(), sqlx::Error> {
             Command::new("images")
                 .about("Images")
         )
+        .subcommand(
+            Command::new("covers")
+                .about("Move the cover images to another store")
+                .arg(Arg::new("from").required(true).help("postgres, filesystem or s3"))
+                .arg(Arg::new("to").required(true).help("postgres, filesystem or s3"))
+        )
         .get_matches();
 
     // Handle subcommands
@@ -98,6 +109,18 @@ pub async fn main() -> Result<(), sqlx::Error> {
                 }
             }
         }
+        Some(("covers", sub_matches)) => {
+            let from = sub_matches.get_one::<String>("from").unwrap();
+            let to = sub_matches.get_one::<String>("to").unwrap();
+            match move_covers(&args, from, to).await {
+                Ok(_) => {
+                    info!("Done moving the covers");
+                }
+                Err(e) => {
+                    error!("Error while moving the covers: {e}");
+                }
+            }
+        }
         Some(("xml", _)) => {
             info!("Exporting XML");
             match to_xml(&args).await {
@@ -151,6 +174,20 @@ pub struct Config {
     prod_url: String,
     #[serde(default = "default_local_url")]
     local_url: String,
+    /// Where the "filesystem" cover store is, "covers" by default
+    cover_dir: Option<String>,
+    s3: Option<S3Config>,
+}
+
+/// The [s3] section of db.toml, for the "s3" cover store. `endpoint` is only needed for MinIO
+/// and other S3-compatible services.
+#[derive(Default, Deserialize)]
+pub struct S3Config {
+    bucket: String,
+    endpoint: Option<String>,
+    region: Option<String>,
+    access_key_id: String,
+    secret_access_key: String,
 }
 
 fn default_local_url() -> String {
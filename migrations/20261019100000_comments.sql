@@ -0,0 +1,16 @@
+-- Reader comments on summaries
+
+CREATE TABLE IF NOT EXISTS comments (
+    id serial PRIMARY KEY,
+    number integer NOT NULL,
+    parent_id integer REFERENCES comments(id) ON DELETE CASCADE,
+    login character varying(40) DEFAULT NULL::character varying,
+    author_name character varying(80) DEFAULT ''::character varying NOT NULL,
+    author_email character varying(60) DEFAULT ''::character varying NOT NULL,
+    text text NOT NULL,
+    status character varying(20) DEFAULT 'pending'::character varying NOT NULL,
+    verification_token character varying(60) DEFAULT NULL::character varying,
+    date character varying(40) DEFAULT ''::character varying NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS comments_number_idx ON comments (number);
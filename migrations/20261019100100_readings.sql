@@ -0,0 +1,11 @@
+-- Per-user reading status and rating of each book
+
+CREATE TABLE IF NOT EXISTS readings (
+    login character varying(40) NOT NULL REFERENCES users(login) ON DELETE CASCADE,
+    number integer NOT NULL,
+    status character varying(20) DEFAULT 'unread'::character varying NOT NULL,
+    rating integer CHECK (rating BETWEEN 1 AND 5),
+    PRIMARY KEY (login, number)
+);
+
+CREATE INDEX IF NOT EXISTS readings_number_idx ON readings (number);
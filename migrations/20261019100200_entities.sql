@@ -0,0 +1,22 @@
+-- Characters, places and organizations mentioned in the summaries
+
+CREATE TABLE IF NOT EXISTS entities (
+    id serial PRIMARY KEY,
+    slug character varying(80) NOT NULL UNIQUE,
+    name character varying(80) NOT NULL,
+    kind character varying(20) DEFAULT 'character'::character varying NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS entity_aliases (
+    entity_id integer NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    alias character varying(80) NOT NULL,
+    PRIMARY KEY (entity_id, alias)
+);
+
+CREATE TABLE IF NOT EXISTS summary_entities (
+    number integer NOT NULL,
+    entity_id integer NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    PRIMARY KEY (number, entity_id)
+);
+
+CREATE INDEX IF NOT EXISTS summary_entities_entity_idx ON summary_entities (entity_id);
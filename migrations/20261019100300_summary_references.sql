@@ -0,0 +1,9 @@
+-- Backlinks index: which summaries mention which books ("see #1234", "as in PR 2991")
+
+CREATE TABLE IF NOT EXISTS summary_references (
+    from_number integer NOT NULL,
+    to_number integer NOT NULL,
+    PRIMARY KEY (from_number, to_number)
+);
+
+CREATE INDEX IF NOT EXISTS summary_references_to_idx ON summary_references (to_number);
@@ -0,0 +1,20 @@
+-- Books, summaries, cycles and covers are identified by (series, number). Everything that
+-- existed before belongs to the main series.
+
+ALTER TABLE hefte ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE hefte DROP CONSTRAINT hefte_pkey;
+ALTER TABLE hefte ADD PRIMARY KEY (series, number);
+
+ALTER TABLE summaries ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE summaries DROP CONSTRAINT summaries_pkey;
+ALTER TABLE summaries ADD PRIMARY KEY (series, number);
+
+ALTER TABLE cycles ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE cycles DROP CONSTRAINT cycles_pkey;
+ALTER TABLE cycles ADD PRIMARY KEY (series, number);
+
+ALTER TABLE covers ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE covers DROP CONSTRAINT covers_pkey;
+ALTER TABLE covers ADD PRIMARY KEY (series, number);
+
+ALTER TABLE pending ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
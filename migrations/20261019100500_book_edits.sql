@@ -0,0 +1,14 @@
+-- Audit trail of the changes made to the hefte rows, one row per modified field
+
+CREATE TABLE IF NOT EXISTS book_edits (
+    id serial PRIMARY KEY,
+    series character varying(10) DEFAULT 'PR'::character varying NOT NULL,
+    number integer NOT NULL,
+    login character varying(40) DEFAULT NULL::character varying,
+    field character varying(20) NOT NULL,
+    old_value text,
+    new_value text,
+    date character varying(40) DEFAULT ''::character varying NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS book_edits_book_idx ON book_edits (series, number);
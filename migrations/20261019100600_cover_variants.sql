@@ -0,0 +1,14 @@
+-- covers.image keeps the original image as it was downloaded. Each original is resized once
+-- into every (size, format) combination the site serves, stored here.
+
+CREATE TABLE IF NOT EXISTS cover_variants (
+    series character varying(10) NOT NULL,
+    number integer NOT NULL,
+    size character varying(10) NOT NULL,
+    format character varying(10) NOT NULL,
+    width integer NOT NULL,
+    height integer NOT NULL,
+    image bytea NOT NULL,
+    PRIMARY KEY (series, number, size, format),
+    FOREIGN KEY (series, number) REFERENCES covers (series, number) ON DELETE CASCADE
+);
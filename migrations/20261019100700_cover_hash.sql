@@ -0,0 +1,6 @@
+-- SHA-256 of covers.image, the ETag of the cover and of its resized variants. Reading it is
+-- much cheaper than reading the image, which answers most requests with a 304.
+
+ALTER TABLE covers ADD COLUMN IF NOT EXISTS hash character varying(64);
+
+UPDATE covers SET hash = encode(sha256(image), 'hex') WHERE hash IS NULL AND image IS NOT NULL;
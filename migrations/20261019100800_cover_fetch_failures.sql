@@ -0,0 +1,12 @@
+-- Covers the background prefetch couldn't download. A book isn't tried again before
+-- next_attempt, which moves further away after each failure.
+
+CREATE TABLE IF NOT EXISTS cover_fetch_failures (
+    series character varying(10) NOT NULL,
+    number integer NOT NULL,
+    attempts integer DEFAULT 1 NOT NULL,
+    last_error text NOT NULL,
+    last_attempt timestamp with time zone DEFAULT now() NOT NULL,
+    next_attempt timestamp with time zone NOT NULL,
+    PRIMARY KEY (series, number)
+);
@@ -0,0 +1,4 @@
+-- Where a cover comes from: downloaded from PerryPedia, or uploaded by an admin. Manual covers
+-- are never replaced by a download.
+
+ALTER TABLE covers ADD COLUMN IF NOT EXISTS source character varying(20) DEFAULT 'perrypedia' NOT NULL;
@@ -0,0 +1,4 @@
+-- The images of the covers can live outside of Postgres (COVER_STORE=filesystem or s3), the
+-- covers and cover_variants rows then only describe them and their image column is null.
+
+ALTER TABLE cover_variants ALTER COLUMN image DROP NOT NULL;
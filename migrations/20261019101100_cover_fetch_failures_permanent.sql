@@ -0,0 +1,3 @@
+-- Failures that will happen again with the same image (not an image, too large, corrupt):
+-- the prefetch stops retrying them and the pages show the placeholder
+ALTER TABLE cover_fetch_failures ADD COLUMN permanent boolean NOT NULL DEFAULT false;
@@ -0,0 +1,14 @@
+-- What the cover report needs to spot wrong covers: the dimensions of the original and its
+-- perceptual hash (64 bits), filled when a cover is stored or when the report runs
+ALTER TABLE covers ADD COLUMN width integer;
+ALTER TABLE covers ADD COLUMN height integer;
+ALTER TABLE covers ADD COLUMN phash bigint;
+
+-- Perceptual hashes of images that aren't covers (the PerryPedia "no cover yet" graphic...),
+-- marked by an admin from the report, with the book they were first seen on
+CREATE TABLE IF NOT EXISTS cover_placeholder_hashes (
+    phash bigint PRIMARY KEY,
+    series varchar NOT NULL,
+    number integer NOT NULL,
+    created timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,22 @@
+-- Comments, readings, tags and references are identified by (series, number) too, like the
+-- books and summaries they belong to. Everything that existed before is about the main series.
+
+ALTER TABLE comments ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+DROP INDEX IF EXISTS comments_number_idx;
+CREATE INDEX comments_number_idx ON comments (series, number);
+
+ALTER TABLE readings ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE readings DROP CONSTRAINT readings_pkey;
+ALTER TABLE readings ADD PRIMARY KEY (login, series, number);
+DROP INDEX IF EXISTS readings_number_idx;
+CREATE INDEX readings_number_idx ON readings (series, number);
+
+ALTER TABLE summary_entities ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE summary_entities DROP CONSTRAINT summary_entities_pkey;
+ALTER TABLE summary_entities ADD PRIMARY KEY (series, number, entity_id);
+
+ALTER TABLE summary_references ADD COLUMN series VARCHAR(10) NOT NULL DEFAULT 'PR';
+ALTER TABLE summary_references DROP CONSTRAINT summary_references_pkey;
+ALTER TABLE summary_references ADD PRIMARY KEY (series, from_number, to_number);
+DROP INDEX IF EXISTS summary_references_to_idx;
+CREATE INDEX summary_references_to_idx ON summary_references (series, to_number);
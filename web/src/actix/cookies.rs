@@ -4,7 +4,7 @@ use actix_web::cookie::{Cookie};
 use actix_web::cookie::time::OffsetDateTime;
 use actix_web::HttpRequest;
 use async_trait::async_trait;
-use crate::{CookieManager, COOKIE_AUTH_TOKEN};
+use crate::{CookieManager, COOKIE_AUTH_TOKEN, COOKIE_READ_UP_TO};
 use crate::db::Db;
 use crate::entities::User;
 
@@ -42,4 +42,17 @@ impl CookieManager<Cookie<'static>> for ActixCookies {
             .expires(OffsetDateTime::now_utc() + Duration::from_secs(60 * 60 * 24 * days as u64))
             .finish()
     }
-}
\ No newline at end of file
+
+    async fn find_read_up_to(&self) -> Option<u32> {
+        self.cookies.iter().find(|c| c.name() == COOKIE_READ_UP_TO)
+            .and_then(|c| c.value().parse::<u32>().ok())
+    }
+
+    async fn create_read_up_to_cookie(&self, read_up_to: Option<u32>) -> Cookie<'static> {
+        let days = if read_up_to.is_some() { 365 * 10 } else { 0 };
+        Cookie::build(COOKIE_READ_UP_TO, read_up_to.map_or("".into(), |n| n.to_string()))
+            .path("/")
+            .expires(OffsetDateTime::now_utc() + Duration::from_secs(60 * 60 * 24 * days))
+            .finish()
+    }
+}
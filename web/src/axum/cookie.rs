@@ -4,7 +4,7 @@ use axum_extra::extract::cookie::Cookie;
 use axum_extra::extract::CookieJar;
 use cookie::time::Duration;
 use tracing::trace;
-use crate::{CookieManager, COOKIE_AUTH_TOKEN};
+use crate::{CookieManager, COOKIE_AUTH_TOKEN, COOKIE_READ_UP_TO};
 use crate::db::Db;
 use crate::entities::User;
 
@@ -38,4 +38,16 @@ impl CookieManager<Cookie<'static>> for AxumCookies {
             .max_age(Duration::days(days as i64))  // 365 days in seconds
             .into()
     }
-}
\ No newline at end of file
+
+    async fn find_read_up_to(&self) -> Option<u32> {
+        self.cookies.get(COOKIE_READ_UP_TO).and_then(|c| c.value().parse::<u32>().ok())
+    }
+
+    async fn create_read_up_to_cookie(&self, read_up_to: Option<u32>) -> Cookie<'static> {
+        let days = if read_up_to.is_some() { 365 * 10 } else { 0 };
+        Cookie::build((COOKIE_READ_UP_TO, read_up_to.map_or("".into(), |n| n.to_string())))
+            .path("/")
+            .max_age(Duration::days(days))
+            .into()
+    }
+}
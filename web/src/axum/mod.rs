@@ -3,35 +3,52 @@ mod response;
 
 use std::net::SocketAddr;
 use std::time::Instant;
-use axum::extract::{Path, Query, State};
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
 use axum::response::{IntoResponse, Response};
 use axum::{Form, Router};
 use axum::routing::{get, post};
+use sha2::{Digest, Sha256};
 use tower_http::services::{ServeDir, ServeFile};
 use crate::config::Config;
 use crate::{CookieManager, PerryState};
 
-use axum::{http::{Request}};
+use axum::{http::{header, HeaderMap, HeaderName, HeaderValue, Request, StatusCode}};
 use axum::body::Body;
 use axum::middleware::{from_fn, Next};
 use axum_extra::extract::CookieJar;
 use tracing::{debug, info, warn};
 use crate::axum::cookie::AxumCookies;
 use crate::axum::response::{AxumResponse};
-use crate::covers::{cover_logic, delete_cover_logic};
+use crate::covers::{cover_logic, delete_cover_logic, etag_matches, negotiate_format, CoverQueryParams,
+    DeleteCoverQueryParams, MAX_COVER_BYTES};
 use crate::email::api_send_email_logic;
-use crate::logic::{login_logic, LoginFormData};
-use crate::pages::cycle::cycle_logic;
-use crate::pages::cycles::{api_cycles_logic, index_logic, insert_cycle_logic, CycleFormData};
-use crate::pages::edit::{edit_summary_logic, FormData};
+use crate::logic::{login_logic, LoginFormData, ReadUpToFormData};
+use crate::pages::comments::{api_comments_logic, approve_comment_logic, delete_comment_logic, moderation_logic, post_comment_logic, verify_comment_logic, CommentFormData};
+use crate::pages::characters::{character_logic, insert_entity_logic, post_summary_entities_logic, summary_entities_logic, EntityFormData};
+use crate::pages::cycle::{cycle_covers_logic, cycle_logic, cycle_montage_logic};
+use crate::pages::cycles::{api_cycles_logic, apply_cycle_import_logic, cycles_coverage_logic,
+    cycles_import_logic, delete_cycle_logic, edit_cycle_logic, index_logic, insert_cycle_form_logic,
+    insert_cycle_logic, post_edit_cycle_logic, CycleDeleteFormData, CycleFormData};
+use crate::pages::covers::{cover_prefetch_logic, cover_report_logic, cover_upload_logic, forget_placeholder_logic,
+    mark_placeholder_logic, post_cover_upload_logic, refetch_cover_logic, report_delete_cover_logic};
+use crate::pages::books::{api_book_logic, api_post_book_logic, books_audit_logic, edit_book_logic,
+    post_edit_book_logic, BookFormData};
+use crate::pages::edit::{edit_summary_logic, refresh_metadata_logic, FormData};
 use crate::pages::pending::pending_logic;
+use crate::pages::reading::{api_reading_logic, post_reading_logic, reading_progress_logic, ReadingFormData};
 use crate::pages::summaries::{api_summaries_logic, DisplaySummaryQueryParams, php_display_summary_logic, post_summary_logic, SingleSummaryData, summaries_logic, summaries_post_logic};
+use crate::references::rebuild_references_logic;
+use crate::entities::Series;
 use crate::url::Urls;
 use crate::axum::response::WrappedPrResult;
 
 pub async fn main_axum(config: Config, state: PerryState) -> std::io::Result<()> {
     info!("Starting axum");
     let serve_dir = ServeDir::new("web/static").not_found_service(ServeFile::new("static"));
+    // ServeDir answers If-Modified-Since but sends neither ETag nor Cache-Control
+    let static_files = Router::new()
+        .fallback_service(serve_dir)
+        .layer(from_fn(static_cache_middleware));
 
     // #[instrument(target = "url", skip_all)]
     async fn log_middleware(request: Request<Body>, next: Next) -> Response {
@@ -45,7 +62,7 @@ pub async fn main_axum(config: Config, state: PerryState) -> std::io::Result<()>
 
     let app = Router::new()
         // Static files
-        .nest_service("/static", serve_dir.clone())
+        .nest_service("/static", static_files)
 
         //
         // URL's
@@ -57,16 +74,67 @@ pub async fn main_axum(config: Config, state: PerryState) -> std::io::Result<()>
         // Cycles
         .route("/", get(index).head(root_head))
         .route("/cycles/{number}", get(cycle))
+        .route("/cycles/{number}/covers", get(cycle_covers))
+        .route("/cycles/{number}/covers/montage", get(cycle_montage))
         .route("/api/cycles/{number}", get(api_cycle))
         .route("/cycles/insert", get(cycles_insert_form).post(cycles_insert))
+        .route("/cycles/coverage", get(cycles_coverage))
+        .route("/cycles/import", get(cycles_import).post(cycles_import_apply))
+        // Books
+        .route("/books/audit", get(books_audit))
+        .route("/books/{number}/edit", get(edit_book).post(post_edit_book))
+        .route("/api/books/{number}", get(api_book).post(api_post_book))
+        .route("/{series}/books/audit", get(series_books_audit))
+        .route("/{series}/books/{number}/edit", get(series_edit_book).post(series_post_edit_book))
+        .route("/api/{series}/books/{number}", get(api_series_book).post(api_series_post_book))
+        .route("/cycles/{number}/edit", get(cycles_edit_form).post(cycles_edit))
+        .route("/cycles/{number}/delete", post(cycles_delete))
+        .route("/{series}/", get(series_index))
+        .route("/{series}/cycles/{number}", get(series_cycle))
+        .route("/{series}/cycles/{number}/covers", get(series_cycle_covers))
+        .route("/{series}/cycles/{number}/covers/montage", get(series_cycle_montage))
+        .route("/api/{series}/cycles/{number}", get(api_series_cycle))
+        .route("/{series}/cycles/coverage", get(series_cycles_coverage))
+        .route("/{series}/cycles/import", get(series_cycles_import).post(series_cycles_import_apply))
+        .route("/{series}/cycles/{number}/edit", get(series_cycles_edit_form).post(series_cycles_edit))
+        .route("/{series}/cycles/{number}/delete", post(series_cycles_delete))
 
         // Summaries
         .route("/summaries", post(summaries_post))
         .route("/summaries/{number}", get(summaries))
         .route("/summaries/{number}/edit", get(edit_summary))
+        .route("/summaries/{number}/metadata", post(refresh_metadata))
         .route("/api/summaries", post(post_summary))
         .route("/api/summaries/{number}", get(api_summaries))
         .route("/api/sendEmail/{number}", get(api_send_email))
+        .route("/{series}/summaries/{number}", get(series_summaries))
+        .route("/{series}/summaries/{number}/edit", get(series_edit_summary))
+        .route("/{series}/summaries/{number}/metadata", post(series_refresh_metadata))
+        .route("/api/{series}/summaries/{number}", get(api_series_summaries))
+        .route("/api/{series}/sendEmail/{number}", get(api_series_send_email))
+
+        // Comments
+        .route("/api/summaries/{number}/comments", get(api_comments).post(post_comment))
+        .route("/api/{series}/summaries/{number}/comments", get(api_series_comments).post(series_post_comment))
+        .route("/comments/verify/{token}", get(verify_comment))
+        .route("/comments/moderation", get(comments_moderation))
+        .route("/comments/{id}/approve", get(approve_comment))
+        .route("/comments/{id}/delete", get(delete_comment))
+
+        // Reading status and ratings
+        .route("/reading", get(reading_progress))
+        .route("/api/summaries/{number}/reading", get(api_reading).post(post_reading))
+        .route("/{series}/reading", get(series_reading_progress))
+        .route("/api/{series}/summaries/{number}/reading", get(api_series_reading).post(series_post_reading))
+
+        // Characters, places and organizations
+        .route("/characters", post(insert_entity))
+        .route("/characters/{slug}", get(character))
+        .route("/summaries/{number}/entities", get(summary_entities).post(post_summary_entities))
+        .route("/{series}/summaries/{number}/entities", get(series_summary_entities).post(series_post_summary_entities))
+
+        // Backlinks index
+        .route("/references/rebuild", get(rebuild_references))
 
         // Pending
         .route("/pending", get(pending))
@@ -78,9 +146,25 @@ pub async fn main_axum(config: Config, state: PerryState) -> std::io::Result<()>
         .route("/login", post(login))
         .route("/logout", get(logout))
 
+        // Spoiler-safe mode
+        .route("/readUpTo", post(read_up_to))
+
         // Covers
         .route("/covers/{number}", get(cover))
-        .route("/covers/{number}/delete", get(delete_cover))
+        .route("/covers/prefetch", get(cover_prefetch))
+        .route("/covers/report", get(cover_report))
+        .route("/covers/placeholders/{phash}/delete", post(forget_placeholder))
+        .route("/covers/{number}/delete", get(delete_cover).post(report_delete_cover))
+        .route("/covers/{number}/refetch", post(refetch_cover))
+        .route("/covers/{number}/placeholder", post(mark_placeholder))
+        .route("/covers/{number}/upload", get(cover_upload).post(post_cover_upload)
+            .layer(DefaultBodyLimit::max(MAX_COVER_BYTES + 64 * 1024)))
+        .route("/{series}/covers/{number}", get(series_cover))
+        .route("/{series}/covers/{number}/delete", get(series_delete_cover).post(series_report_delete_cover))
+        .route("/{series}/covers/{number}/refetch", post(series_refetch_cover))
+        .route("/{series}/covers/{number}/placeholder", post(series_mark_placeholder))
+        .route("/{series}/covers/{number}/upload", get(series_cover_upload).post(series_post_cover_upload)
+            .layer(DefaultBodyLimit::max(MAX_COVER_BYTES + 64 * 1024)))
 
         // PHP backward compatibility
 
@@ -114,7 +198,36 @@ pub async fn main_axum(config: Config, state: PerryState) -> std::io::Result<()>
 
 async fn favicon() -> Response {
     let favicon = include_bytes!("../../static/favicon.png");
-    AxumResponse::png(favicon.into())
+    AxumResponse::image(favicon.into())
+}
+
+/// The static files aren't fingerprinted, so browsers only keep them for a day before checking
+/// their ETag, which is derived from the modification date and the length of the file
+async fn static_cache_middleware(request: Request<Body>, next: Next) -> Response {
+    const STATIC_CACHE_CONTROL: &str = "public, max-age=86400";
+    let if_none_match = header_value(request.headers(), header::IF_NONE_MATCH).map(String::from);
+    let mut response = next.run(request).await;
+    if response.status() != StatusCode::OK && response.status() != StatusCode::NOT_MODIFIED {
+        return response;
+    }
+
+    let headers = response.headers();
+    let etag = match (header_value(headers, header::LAST_MODIFIED), header_value(headers, header::CONTENT_LENGTH)) {
+        (Some(modified), Some(length)) => {
+            let hash = Sha256::new().chain_update(modified).chain_update(length).finalize();
+            Some(format!("\"{}\"", &format!("{hash:x}")[..16]))
+        }
+        _ => { None }
+    };
+    if let Some(etag) = etag {
+        if if_none_match.is_some_and(|h| etag_matches(&h, &etag)) {
+            return AxumResponse::not_modified(etag, STATIC_CACHE_CONTROL);
+        }
+        response.headers_mut().insert(header::ETAG, etag.parse().unwrap());
+    }
+    response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static(STATIC_CACHE_CONTROL));
+
+    response
 }
 
 async fn root_head() -> impl IntoResponse {
@@ -129,19 +242,85 @@ macro_rules! wrap {
 }
 
 async fn index(State(state): State<PerryState>, jar: CookieJar) -> Response {
-    wrap!(index_logic(&state, AxumCookies::new(jar)), state)
+    wrap!(index_logic(&state, AxumCookies::new(jar), Series::Pr), state)
+}
+
+async fn series_index(State(state): State<PerryState>, jar: CookieJar, Path(series): Path<Series>) -> Response {
+    wrap!(index_logic(&state, AxumCookies::new(jar), series), state)
+}
+
+async fn cycle(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>)
+    -> impl IntoResponse
+{
+    wrap!(cycle_logic(&state, AxumCookies::new(jar), Series::Pr, number), state)
+}
+
+async fn series_cycle(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>)
+    -> impl IntoResponse
+{
+    wrap!(cycle_logic(&state, AxumCookies::new(jar), series, number), state)
+}
+
+async fn cycle_covers(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>) -> Response {
+    wrap!(cycle_covers_logic(&state, AxumCookies::new(jar), Series::Pr, number), state)
+}
+
+async fn series_cycle_covers(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(cycle_covers_logic(&state, AxumCookies::new(jar), series, number), state)
+}
+
+async fn cycle_montage(State(state): State<PerryState>, headers: HeaderMap, Path(number): Path<u32>)
+    -> Response
+{
+    let if_none_match = header_value(&headers, header::IF_NONE_MATCH).map(String::from);
+    wrap!(cycle_montage_logic(&state, Series::Pr, number, if_none_match), state)
 }
 
-async fn cycle(State(state): State<PerryState>, jar: CookieJar) -> impl IntoResponse {
-    wrap!(cycle_logic(&state, AxumCookies::new(jar)), state)
+async fn series_cycle_montage(State(state): State<PerryState>, headers: HeaderMap,
+        Path((series, number)): Path<(Series, u32)>)
+    -> Response
+{
+    let if_none_match = header_value(&headers, header::IF_NONE_MATCH).map(String::from);
+    wrap!(cycle_montage_logic(&state, series, number, if_none_match), state)
 }
 
-async fn api_cycle(State(state): State<PerryState>, Path(number): Path<u32>) -> impl IntoResponse {
-    wrap!(api_cycles_logic(&state, number), state)
+async fn api_cycle(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>)
+    -> impl IntoResponse
+{
+    wrap!(api_cycles_logic(&state, AxumCookies::new(jar), Series::Pr, number), state)
 }
 
-async fn cover(State(state): State<PerryState>, Path(book_number): Path<u32>) -> Response {
-    wrap!(cover_logic(&state, book_number), state)
+async fn api_series_cycle(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>)
+    -> impl IntoResponse
+{
+    wrap!(api_cycles_logic(&state, AxumCookies::new(jar), series, number), state)
+}
+
+async fn cover(State(state): State<PerryState>, headers: HeaderMap, Path(book_number): Path<u32>,
+        Query(params): Query<CoverQueryParams>)
+    -> Response
+{
+    let format = negotiate_format(params.format, header_value(&headers, header::ACCEPT));
+    let if_none_match = header_value(&headers, header::IF_NONE_MATCH).map(String::from);
+    wrap!(cover_logic(&state, Series::Pr, book_number, params.size, format, if_none_match), state)
+}
+
+async fn series_cover(State(state): State<PerryState>, headers: HeaderMap,
+        Path((series, book_number)): Path<(Series, u32)>, Query(params): Query<CoverQueryParams>)
+    -> Response
+{
+    let format = negotiate_format(params.format, header_value(&headers, header::ACCEPT));
+    let if_none_match = header_value(&headers, header::IF_NONE_MATCH).map(String::from);
+    wrap!(cover_logic(&state, series, book_number, params.size, format, if_none_match), state)
+}
+
+fn header_value(headers: &HeaderMap, name: HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
 }
 
 async fn summaries_post(State(state): State<PerryState>, Form(form_data): Form<SingleSummaryData>)
@@ -150,14 +329,43 @@ async fn summaries_post(State(state): State<PerryState>, Form(form_data): Form<S
     wrap!(summaries_post_logic(form_data), state)
 }
 
-async fn summaries(State(state): State<PerryState>, jar: CookieJar) -> impl IntoResponse {
-    wrap!(summaries_logic(&state, AxumCookies::new(jar)), state)
+async fn summaries(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> impl IntoResponse
+{
+    wrap!(summaries_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_summaries(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> impl IntoResponse
+{
+    wrap!(summaries_logic(&state, AxumCookies::new(jar), series, book_number), state)
 }
 
 async fn edit_summary(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
     -> impl IntoResponse
 {
-    wrap!(edit_summary_logic(&state, AxumCookies::new(jar), book_number), state)
+    wrap!(edit_summary_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_edit_summary(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> impl IntoResponse
+{
+    wrap!(edit_summary_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn refresh_metadata(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> Response
+{
+    wrap!(refresh_metadata_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_refresh_metadata(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(refresh_metadata_logic(&state, AxumCookies::new(jar), series, book_number), state)
 }
 
 async fn post_summary(State(state): State<PerryState>, jar: CookieJar, Form(form_data): Form<FormData>)
@@ -166,12 +374,147 @@ async fn post_summary(State(state): State<PerryState>, jar: CookieJar, Form(form
     wrap!(post_summary_logic(&state, AxumCookies::new(jar), form_data), state)
 }
 
-async fn api_summaries(State(state): State<PerryState>, Path(book_number): Path<u32>) -> Response {
-    wrap!(api_summaries_logic(&state, book_number), state)
+async fn api_summaries(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> Response
+{
+    wrap!(api_summaries_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn api_series_summaries(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(api_summaries_logic(&state, AxumCookies::new(jar), series, book_number), state)
 }
 
 async fn api_send_email(State(state): State<PerryState>, Path(book_number): Path<u32>) -> Response {
-    wrap!(api_send_email_logic(&state, book_number), state)
+    wrap!(api_send_email_logic(&state, Series::Pr, book_number), state)
+}
+
+async fn api_series_send_email(State(state): State<PerryState>, Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(api_send_email_logic(&state, series, book_number), state)
+}
+
+async fn api_comments(State(state): State<PerryState>, Path(book_number): Path<u32>) -> Response {
+    wrap!(api_comments_logic(&state, Series::Pr, book_number), state)
+}
+
+async fn api_series_comments(State(state): State<PerryState>, Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(api_comments_logic(&state, series, book_number), state)
+}
+
+async fn post_comment(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>,
+        Form(form_data): Form<CommentFormData>)
+    -> Response
+{
+    wrap!(post_comment_logic(&state, AxumCookies::new(jar), Series::Pr, book_number, form_data), state)
+}
+
+async fn series_post_comment(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>, Form(form_data): Form<CommentFormData>)
+    -> Response
+{
+    wrap!(post_comment_logic(&state, AxumCookies::new(jar), series, book_number, form_data), state)
+}
+
+async fn verify_comment(State(state): State<PerryState>, Path(token): Path<String>) -> Response {
+    wrap!(verify_comment_logic(&state, token), state)
+}
+
+async fn comments_moderation(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(moderation_logic(&state, AxumCookies::new(jar)), state)
+}
+
+async fn approve_comment(State(state): State<PerryState>, jar: CookieJar, Path(id): Path<i32>) -> Response {
+    wrap!(approve_comment_logic(&state, AxumCookies::new(jar), id), state)
+}
+
+async fn delete_comment(State(state): State<PerryState>, jar: CookieJar, Path(id): Path<i32>) -> Response {
+    wrap!(delete_comment_logic(&state, AxumCookies::new(jar), id), state)
+}
+
+async fn reading_progress(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(reading_progress_logic(&state, AxumCookies::new(jar), Series::Pr), state)
+}
+
+async fn series_reading_progress(State(state): State<PerryState>, jar: CookieJar, Path(series): Path<Series>)
+    -> Response
+{
+    wrap!(reading_progress_logic(&state, AxumCookies::new(jar), series), state)
+}
+
+async fn api_reading(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> Response
+{
+    wrap!(api_reading_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn api_series_reading(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(api_reading_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn post_reading(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>,
+        Form(form_data): Form<ReadingFormData>)
+    -> Response
+{
+    wrap!(post_reading_logic(&state, AxumCookies::new(jar), Series::Pr, book_number, form_data), state)
+}
+
+async fn series_post_reading(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>, Form(form_data): Form<ReadingFormData>)
+    -> Response
+{
+    wrap!(post_reading_logic(&state, AxumCookies::new(jar), series, book_number, form_data), state)
+}
+
+async fn character(State(state): State<PerryState>, jar: CookieJar, Path(slug): Path<String>)
+    -> Response
+{
+    wrap!(character_logic(&state, AxumCookies::new(jar), slug), state)
+}
+
+async fn insert_entity(State(state): State<PerryState>, jar: CookieJar, Form(form_data): Form<EntityFormData>)
+    -> Response
+{
+    wrap!(insert_entity_logic(&state, AxumCookies::new(jar), form_data), state)
+}
+
+async fn summary_entities(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> Response
+{
+    wrap!(summary_entities_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_summary_entities(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(summary_entities_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn post_summary_entities(State(state): State<PerryState>, jar: CookieJar,
+        Path(book_number): Path<u32>, Form(form_data): Form<Vec<(String, String)>>)
+    -> Response
+{
+    wrap!(post_summary_entities_logic(&state, AxumCookies::new(jar), Series::Pr, book_number, form_data), state)
+}
+
+async fn series_post_summary_entities(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>, Form(form_data): Form<Vec<(String, String)>>)
+    -> Response
+{
+    wrap!(post_summary_entities_logic(&state, AxumCookies::new(jar), series, book_number, form_data), state)
+}
+
+async fn rebuild_references(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(rebuild_references_logic(&state, AxumCookies::new(jar)), state)
 }
 
 async fn pending(State(state): State<PerryState>, jar: CookieJar) -> Response {
@@ -207,14 +550,114 @@ async fn login(State(state): State<PerryState>, jar: CookieJar, Form(form): Form
     }
 }
 
+async fn read_up_to(jar: CookieJar, Form(form): Form<ReadUpToFormData>) -> Response {
+    let cookie_manager = AxumCookies::new(jar);
+    let read_up_to = form.read_up_to.trim().parse::<u32>().ok();
+    info!("Spoiler-safe mode: read up to {read_up_to:?}");
+    let cookie = cookie_manager.create_read_up_to_cookie(read_up_to).await;
+    AxumResponse::cookie(Urls::root(), cookie)
+}
+
 async fn logout(jar: CookieJar) -> Response {
     let cookie_manager = AxumCookies::new(jar);
     let cookie = cookie_manager.clear_auth_token_cookie().await;
     AxumResponse::cookie(Urls::root(), cookie)
 }
 
-async fn delete_cover(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>) -> Response {
-    wrap!(delete_cover_logic(&state, AxumCookies::new(jar), book_number), state)
+async fn cover_upload(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> Response
+{
+    wrap!(cover_upload_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_cover_upload(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(cover_upload_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn post_cover_upload(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>,
+        multipart: Multipart)
+    -> Response
+{
+    let image = read_uploaded_image(multipart).await;
+    wrap!(post_cover_upload_logic(&state, AxumCookies::new(jar), Series::Pr, book_number, image), state)
+}
+
+async fn series_post_cover_upload(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>, multipart: Multipart)
+    -> Response
+{
+    let image = read_uploaded_image(multipart).await;
+    wrap!(post_cover_upload_logic(&state, AxumCookies::new(jar), series, book_number, image), state)
+}
+
+/// The content of the "image" field of the upload form
+async fn read_uploaded_image(mut multipart: Multipart) -> Result<Vec<u8>, String> {
+    loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("image") => {
+                return field.bytes().await.map(|b| b.to_vec()).map_err(|e| e.to_string());
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => { return Err("no image was uploaded".into()); }
+            Err(e) => { return Err(e.to_string()); }
+        }
+    }
+}
+
+async fn delete_cover(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>,
+        Query(params): Query<DeleteCoverQueryParams>)
+    -> Response
+{
+    wrap!(delete_cover_logic(&state, AxumCookies::new(jar), Series::Pr, book_number, params), state)
+}
+
+async fn series_delete_cover(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>, Query(params): Query<DeleteCoverQueryParams>)
+    -> Response
+{
+    wrap!(delete_cover_logic(&state, AxumCookies::new(jar), series, book_number, params), state)
+}
+
+async fn report_delete_cover(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>)
+    -> Response
+{
+    wrap!(report_delete_cover_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_report_delete_cover(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(report_delete_cover_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn refetch_cover(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>) -> Response {
+    wrap!(refetch_cover_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_refetch_cover(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(refetch_cover_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn mark_placeholder(State(state): State<PerryState>, jar: CookieJar, Path(book_number): Path<u32>) -> Response {
+    wrap!(mark_placeholder_logic(&state, AxumCookies::new(jar), Series::Pr, book_number), state)
+}
+
+async fn series_mark_placeholder(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, book_number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(mark_placeholder_logic(&state, AxumCookies::new(jar), series, book_number), state)
+}
+
+async fn forget_placeholder(State(state): State<PerryState>, jar: CookieJar, Path(phash): Path<i64>) -> Response {
+    wrap!(forget_placeholder_logic(&state, AxumCookies::new(jar), phash), state)
 }
 
 async fn php_display_summary(State(state): State<PerryState>, Query(params): Query<DisplaySummaryQueryParams>)
@@ -229,16 +672,7 @@ async fn php_display_summary_fallback() -> Response {
 }
 
 async fn cycles_insert_form(State(state): State<PerryState>, jar: CookieJar) -> Response {
-    let cookie_manager = AxumCookies::new(jar);
-    match cookie_manager.find_user(state.db.clone()).await {
-        Some(u) if u.level == 0 => {
-            let html = include_str!("../../templates/insert_cycle.html");
-            AxumResponse::html(html.to_string())
-        }
-        _ => {
-            AxumResponse::redirect(Urls::root())
-        }
-    }
+    wrap!(insert_cycle_form_logic(&state, AxumCookies::new(jar)), state)
 }
 
 async fn cycles_insert(State(state): State<PerryState>, jar: CookieJar, Form(form_data): Form<CycleFormData>)
@@ -252,3 +686,145 @@ async fn cycles_insert(State(state): State<PerryState>, jar: CookieJar, Form(for
         AxumResponse::redirect(Urls::root())
     }
 }
+
+async fn cycles_edit_form(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>)
+    -> Response
+{
+    wrap!(edit_cycle_logic(&state, AxumCookies::new(jar), Series::Pr, number), state)
+}
+
+async fn series_cycles_edit_form(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(edit_cycle_logic(&state, AxumCookies::new(jar), series, number), state)
+}
+
+async fn cycles_edit(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>,
+        Form(form_data): Form<CycleFormData>)
+    -> Response
+{
+    wrap!(post_edit_cycle_logic(&state, AxumCookies::new(jar), Series::Pr, number, form_data), state)
+}
+
+async fn series_cycles_edit(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>, Form(form_data): Form<CycleFormData>)
+    -> Response
+{
+    wrap!(post_edit_cycle_logic(&state, AxumCookies::new(jar), series, number, form_data), state)
+}
+
+async fn cycles_delete(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>,
+        Form(form_data): Form<CycleDeleteFormData>)
+    -> Response
+{
+    wrap!(delete_cycle_logic(&state, AxumCookies::new(jar), Series::Pr, number, form_data), state)
+}
+
+async fn series_cycles_delete(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>, Form(form_data): Form<CycleDeleteFormData>)
+    -> Response
+{
+    wrap!(delete_cycle_logic(&state, AxumCookies::new(jar), series, number, form_data), state)
+}
+
+async fn cycles_coverage(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(cycles_coverage_logic(&state, AxumCookies::new(jar), Series::Pr), state)
+}
+
+async fn series_cycles_coverage(State(state): State<PerryState>, jar: CookieJar, Path(series): Path<Series>)
+    -> Response
+{
+    wrap!(cycles_coverage_logic(&state, AxumCookies::new(jar), series), state)
+}
+
+async fn cycles_import(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(cycles_import_logic(&state, AxumCookies::new(jar), Series::Pr), state)
+}
+
+async fn series_cycles_import(State(state): State<PerryState>, jar: CookieJar, Path(series): Path<Series>)
+    -> Response
+{
+    wrap!(cycles_import_logic(&state, AxumCookies::new(jar), series), state)
+}
+
+async fn cycles_import_apply(State(state): State<PerryState>, jar: CookieJar,
+        Form(form_data): Form<CycleFormData>)
+    -> Response
+{
+    wrap!(apply_cycle_import_logic(&state, AxumCookies::new(jar), Series::Pr, form_data), state)
+}
+
+async fn series_cycles_import_apply(State(state): State<PerryState>, jar: CookieJar,
+        Path(series): Path<Series>, Form(form_data): Form<CycleFormData>)
+    -> Response
+{
+    wrap!(apply_cycle_import_logic(&state, AxumCookies::new(jar), series, form_data), state)
+}
+
+async fn cover_prefetch(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(cover_prefetch_logic(&state, AxumCookies::new(jar)), state)
+}
+
+async fn cover_report(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(cover_report_logic(&state, AxumCookies::new(jar)), state)
+}
+
+async fn books_audit(State(state): State<PerryState>, jar: CookieJar) -> Response {
+    wrap!(books_audit_logic(&state, AxumCookies::new(jar), Series::Pr), state)
+}
+
+async fn series_books_audit(State(state): State<PerryState>, jar: CookieJar, Path(series): Path<Series>)
+    -> Response
+{
+    wrap!(books_audit_logic(&state, AxumCookies::new(jar), series), state)
+}
+
+async fn edit_book(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>) -> Response {
+    wrap!(edit_book_logic(&state, AxumCookies::new(jar), Series::Pr, number), state)
+}
+
+async fn series_edit_book(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(edit_book_logic(&state, AxumCookies::new(jar), series, number), state)
+}
+
+async fn post_edit_book(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>,
+        Form(form): Form<BookFormData>)
+    -> Response
+{
+    wrap!(post_edit_book_logic(&state, AxumCookies::new(jar), Series::Pr, number, form), state)
+}
+
+async fn series_post_edit_book(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>, Form(form): Form<BookFormData>)
+    -> Response
+{
+    wrap!(post_edit_book_logic(&state, AxumCookies::new(jar), series, number, form), state)
+}
+
+async fn api_book(State(state): State<PerryState>, Path(number): Path<u32>) -> Response {
+    wrap!(api_book_logic(&state, Series::Pr, number), state)
+}
+
+async fn api_series_book(State(state): State<PerryState>, Path((series, number)): Path<(Series, u32)>)
+    -> Response
+{
+    wrap!(api_book_logic(&state, series, number), state)
+}
+
+async fn api_post_book(State(state): State<PerryState>, jar: CookieJar, Path(number): Path<u32>,
+        Form(form): Form<BookFormData>)
+    -> Response
+{
+    wrap!(api_post_book_logic(&state, AxumCookies::new(jar), Series::Pr, number, form), state)
+}
+
+async fn api_series_post_book(State(state): State<PerryState>, jar: CookieJar,
+        Path((series, number)): Path<(Series, u32)>, Form(form): Form<BookFormData>)
+    -> Response
+{
+    wrap!(api_post_book_logic(&state, AxumCookies::new(jar), series, number, form), state)
+}
@@ -1,13 +1,22 @@
 use std::sync::Arc;
 use axum::body::Body;
-use axum::http::{header, StatusCode};
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum_extra::extract::cookie::Cookie;
 use tracing::{debug, error};
+use crate::covers::content_type;
 use crate::email::EmailService;
 use crate::errors::{Error, OkContent, PrResult};
 use crate::url::Urls;
 
+/// The cover URLs aren't versioned and an admin can replace a cover any time: browsers check
+/// the ETag on every use, which is answered from the hash alone
+pub const COVER_CACHE_CONTROL: &str = "public, no-cache";
+/// The real cover can show up any time
+pub const PLACEHOLDER_CACHE_CONTROL: &str = "public, max-age=3600";
+/// Set to "true" on the generated covers, for the clients that want to tell them apart
+pub const PLACEHOLDER_HEADER: &str = "x-cover-placeholder";
+
 #[allow(dead_code)]
 pub struct WrappedPrResult(pub PrResult, pub Arc<Box<dyn EmailService>>);
 
@@ -42,7 +51,19 @@ impl IntoResponse for OkContent {
                 AxumResponse::json(json)
             }
             OkContent::Image(bytes) => {
-                AxumResponse::png(bytes)
+                AxumResponse::image(bytes)
+            }
+            OkContent::CachedImage(bytes, etag) => {
+                AxumResponse::cached_image(bytes, etag)
+            }
+            OkContent::NotModified(etag) => {
+                AxumResponse::not_modified(etag, COVER_CACHE_CONTROL)
+            }
+            OkContent::PlaceholderImage(bytes) => {
+                AxumResponse::placeholder_image(bytes)
+            }
+            OkContent::NotFound => {
+                AxumResponse::not_found()
             }
             OkContent::Redirect(location) => {
                 AxumResponse::redirect(location)
@@ -94,10 +115,46 @@ impl AxumResponse {
             .unwrap()
     }
 
-    pub fn png(bytes: Vec<u8>) -> Response {
+    /// The covers table used to hold PNG's and JPEG's alike, so the type comes from the bytes.
+    /// The format of a cover can depend on the Accept header.
+    pub fn image(bytes: Vec<u8>) -> Response {
+        Response::builder()
+            .header(header::CONTENT_TYPE, content_type(&bytes))
+            .header(header::VARY, "Accept")
+            .body(Body::from(bytes))
+            .unwrap()
+    }
+
+    pub fn cached_image(bytes: Vec<u8>, etag: String) -> Response {
+        let mut response = Self::image(bytes);
+        let headers = response.headers_mut();
+        headers.insert(header::ETAG, etag.parse().unwrap());
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(COVER_CACHE_CONTROL));
+        response
+    }
+
+    pub fn placeholder_image(bytes: Vec<u8>) -> Response {
+        let mut response = Self::image(bytes);
+        let headers = response.headers_mut();
+        headers.insert(PLACEHOLDER_HEADER, HeaderValue::from_static("true"));
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(PLACEHOLDER_CACHE_CONTROL));
+        response
+    }
+
+    pub fn not_found() -> Response {
         Response::builder()
-            .header(header::CONTENT_TYPE, "image/png") // Set the correct content type
-            .body(Body::from(bytes)) // Set the body to the image bytes
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("Not found"))
             .unwrap()
     }
-}
\ No newline at end of file
+
+    pub fn not_modified(etag: String, cache_control: &'static str) -> Response {
+        Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .header(header::CACHE_CONTROL, cache_control)
+            .header(header::VARY, "Accept")
+            .body(Body::empty())
+            .unwrap()
+    }
+}
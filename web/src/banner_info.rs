@@ -3,6 +3,8 @@ use crate::entities::User;
 pub struct BannerInfo {
     pub username: String,
     pub is_admin: bool,
+    /// Can fix book metadata
+    pub is_editor: bool,
     pub admin_text: String,
 
     // adminLink: Option<String>
@@ -15,10 +17,12 @@ pub struct BannerInfo {
 impl BannerInfo {
     pub async fn new(user: Option<User>) -> Self {
         let username = user.clone().map_or("".to_string(), |u| u.name);
-        let is_admin = user.map_or(false, |u| u.level == 0);
+        let is_editor = user.as_ref().is_some_and(|u| u.is_editor());
+        let is_admin = user.map_or(false, |u| u.is_admin());
         Self {
             username,
             is_admin,
+            is_editor,
             admin_text: if is_admin { "Admin".to_string() } else { "".to_string() }
         }
     }
@@ -33,8 +33,65 @@ pub struct Config {
     pub send_emails: bool,
     pub email_username: Option<String>,
     pub email_password: Option<String>,
+    /// Download the missing covers in the background
+    #[serde(default = "default_prefetch_covers")]
+    pub prefetch_covers: bool,
+    /// How many covers the prefetch downloads at the same time
+    #[serde(default = "default_prefetch_concurrency")]
+    pub prefetch_concurrency: usize,
+    /// The wiki, can point to a local mock server
+    #[serde(default = "default_perrypedia_url")]
+    pub perrypedia_url: String,
+    /// Of each request to PerryPedia
+    #[serde(default = "default_perrypedia_timeout_ms")]
+    pub perrypedia_timeout_ms: u64,
+    /// After a timeout, a connection error or a 5xx
+    #[serde(default = "default_perrypedia_retries")]
+    pub perrypedia_retries: u32,
+    /// After that many failed requests in a row, PerryPedia is skipped for
+    /// PERRYPEDIA_BREAKER_COOLDOWN_SECS
+    #[serde(default = "default_perrypedia_breaker_failures")]
+    pub perrypedia_breaker_failures: u32,
+    #[serde(default = "default_perrypedia_breaker_cooldown_secs")]
+    pub perrypedia_breaker_cooldown_secs: u64,
+    /// How long the wiki pages are kept, 0 disables the cache
+    #[serde(default = "default_perrypedia_cache_secs")]
+    pub perrypedia_cache_secs: u64,
+    /// Where the covers come from, asked in that order: "db", "perrypedia", "directory" and
+    /// "placeholder", separated by commas
+    #[serde(default = "default_cover_providers")]
+    pub cover_providers: String,
+    /// For the "directory" provider: COVER_SOURCE_DIR/PR/12.jpg
+    #[serde(default = "default_cover_source_dir")]
+    pub cover_source_dir: String,
+    /// Where the cover images go: "postgres", "filesystem" or "s3"
+    #[serde(default = "default_cover_store")]
+    pub cover_store: String,
+    /// For the "filesystem" store
+    #[serde(default = "default_cover_dir")]
+    pub cover_dir: String,
+    /// For the "s3" store. S3_ENDPOINT is only needed for MinIO and other S3-compatible services.
+    pub s3_bucket: Option<String>,
+    pub s3_endpoint: Option<String>,
+    #[serde(default = "default_s3_region")]
+    pub s3_region: String,
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
 }
 
 fn default_port() -> u16 { 9000 }
 fn default_is_heroku() -> bool { false }
 fn default_send_emails() -> bool { false }
+fn default_prefetch_covers() -> bool { true }
+fn default_prefetch_concurrency() -> usize { 4 }
+fn default_perrypedia_url() -> String { "https://www.perrypedia.de".into() }
+fn default_perrypedia_timeout_ms() -> u64 { 2_000 }
+fn default_perrypedia_retries() -> u32 { 2 }
+fn default_perrypedia_breaker_failures() -> u32 { 5 }
+fn default_perrypedia_breaker_cooldown_secs() -> u64 { 120 }
+fn default_perrypedia_cache_secs() -> u64 { 60 * 60 }
+fn default_cover_providers() -> String { "db,perrypedia,placeholder".into() }
+fn default_cover_source_dir() -> String { "cover-sources".into() }
+fn default_cover_store() -> String { "postgres".into() }
+fn default_cover_dir() -> String { "covers".into() }
+fn default_s3_region() -> String { "us-east-1".into() }
@@ -0,0 +1,10 @@
+//! Where the image of a cover goes in a file system or a bucket. The db tool moving the covers
+//! between stores includes this file too, so both agree on the layout.
+
+/// "PR/12/original" without a variant, "PR/12/thumb.webp" for the (size, format) variant
+pub fn cover_path(series: &str, number: i32, variant: Option<(&str, &str)>) -> String {
+    match variant {
+        None => { format!("{series}/{number}/original") }
+        Some((size, format)) => { format!("{series}/{number}/{size}.{format}") }
+    }
+}
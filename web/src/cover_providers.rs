@@ -0,0 +1,330 @@
+use std::io::Cursor;
+use std::process::exit;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use async_trait::async_trait;
+use image::ImageFormat;
+use tracing::{debug, error, info, warn};
+use crate::config::Config;
+use crate::cover_store::CoverStore;
+use crate::db::Db;
+use crate::entities::{CoverKey, CoverSize, CoverSource, Series};
+use crate::errors::Error;
+use crate::covers::MAX_COVER_BYTES;
+use crate::errors::Error::{CouldNotFindCoverImage, CoverNotFound, CoverTooLarge, ResizingCover};
+use crate::perrypedia::{CoverFinder, DownloadError, PerryPedia};
+use crate::placeholder::draw_placeholder;
+
+/// The original of a cover, as one of the providers found it
+#[derive(Clone, Debug)]
+pub struct FoundCover {
+    pub image: Vec<u8>,
+    /// Where it was downloaded from
+    pub url: Option<String>,
+    pub source: CoverSource,
+    /// Already in the CoverStore, there is nothing to save
+    pub stored: bool,
+}
+
+/// One of the places the originals of the covers come from
+#[async_trait]
+pub trait CoverProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// `Ok(None)` when that provider doesn't have the cover, the next one is then asked
+    async fn find_cover(&self, series: Series, n: u32) -> Result<Option<FoundCover>, Error>;
+    /// For the covers that were stored without the URL they came from
+    async fn find_cover_url(&self, _series: Series, _n: u32) -> Option<String> { None }
+    /// Providers that make up a cover rather than find one
+    fn generated(&self) -> bool { false }
+    /// Providers that return the covers found earlier
+    fn stored(&self) -> bool { false }
+}
+
+/// Which providers CoverProviders::find_cover() asks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoverLookup {
+    /// All of them, in order
+    All,
+    /// The ones that find real covers, for the prefetch
+    Found,
+    /// The placeholder, once the real cover turned out to be unusable
+    Generated,
+    /// The ones that find real covers, except the stored ones, to replace a wrong cover
+    Fresh,
+}
+
+/// What a provider answered since the server started
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverProviderStats {
+    pub name: &'static str,
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// The providers, asked in order until one of them has the cover
+pub struct CoverProviders {
+    providers: Vec<(Box<dyn CoverProvider>, Counters)>,
+}
+
+impl CoverProviders {
+    pub fn new(providers: Vec<Box<dyn CoverProvider>>) -> Self {
+        Self { providers: providers.into_iter().map(|p| (p, Counters::default())).collect() }
+    }
+
+    pub async fn find_cover(&self, series: Series, n: u32, lookup: CoverLookup) -> Result<FoundCover, Error> {
+        let mut error = None;
+        for (provider, counters) in &self.providers {
+            let asked = match lookup {
+                CoverLookup::All => { true }
+                CoverLookup::Found => { ! provider.generated() }
+                CoverLookup::Generated => { provider.generated() }
+                CoverLookup::Fresh => { ! provider.generated() && ! provider.stored() }
+            };
+            if ! asked {
+                continue;
+            }
+            match provider.find_cover(series, n).await {
+                Ok(Some(cover)) => {
+                    debug!("Cover {series} {n} found by {}", provider.name());
+                    counters.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(cover);
+                }
+                Ok(None) => {
+                    counters.misses.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    warn!("Cover provider {} failed for {series} {n}: {e}", provider.name());
+                    counters.errors.fetch_add(1, Ordering::Relaxed);
+                    error = Some(e);
+                }
+            }
+        }
+
+        // The last error says more than "not found"
+        Err(error.unwrap_or(CoverNotFound(n as i32)))
+    }
+
+    pub async fn find_cover_url(&self, series: Series, n: u32) -> Option<String> {
+        for (provider, _) in &self.providers {
+            if let Some(url) = provider.find_cover_url(series, n).await {
+                return Some(url);
+            }
+        }
+        None
+    }
+
+    pub fn stats(&self) -> Vec<CoverProviderStats> {
+        self.providers.iter().map(|(provider, counters)| CoverProviderStats {
+            name: provider.name(),
+            hits: counters.hits.load(Ordering::Relaxed),
+            misses: counters.misses.load(Ordering::Relaxed),
+            errors: counters.errors.load(Ordering::Relaxed),
+        }).collect()
+    }
+}
+
+/// COVER_PROVIDERS lists the providers in the order they are asked, separated by commas:
+/// "db" (the covers already stored), "perrypedia", "directory" (in COVER_SOURCE_DIR) and
+/// "placeholder"
+pub fn create_cover_providers(config: &Config, db: Arc<Box<dyn Db>>, store: Arc<Box<dyn CoverStore>>,
+        perry_pedia: PerryPedia)
+    -> CoverProviders
+{
+    let mut providers: Vec<Box<dyn CoverProvider>> = Vec::new();
+    for name in config.cover_providers.split(',').map(|s| s.trim()).filter(|s| ! s.is_empty()) {
+        let provider: Box<dyn CoverProvider> = match name {
+            "db" => { Box::new(DbCoverProvider { db: db.clone(), store: store.clone() }) }
+            "perrypedia" => { Box::new(perry_pedia.clone()) }
+            "directory" => { Box::new(DirectoryCoverProvider { dir: config.cover_source_dir.clone() }) }
+            "placeholder" => { Box::new(PlaceholderCoverProvider { db: db.clone() }) }
+            other => {
+                error!("Unknown cover provider {other}, expected db, perrypedia, directory or placeholder");
+                exit(1);
+            }
+        };
+        providers.push(provider);
+    }
+    let result = CoverProviders::new(providers);
+    info!("Cover providers: {}", result.stats().iter().map(|s| s.name).collect::<Vec<_>>().join(", "));
+    result
+}
+
+/// The covers that were already found, in the database and the CoverStore
+pub struct DbCoverProvider {
+    pub db: Arc<Box<dyn Db>>,
+    pub store: Arc<Box<dyn CoverStore>>,
+}
+
+#[async_trait]
+impl CoverProvider for DbCoverProvider {
+    fn name(&self) -> &'static str { "db" }
+
+    async fn find_cover(&self, series: Series, n: u32) -> Result<Option<FoundCover>, Error> {
+        let Some(cover) = self.db.find_cover(series, n).await else {
+            return Ok(None);
+        };
+        match self.store.get(CoverKey::original(series, n as i32)).await {
+            Some(image) => {
+                Ok(Some(FoundCover { image, url: cover.url, source: cover.source, stored: true }))
+            }
+            None => {
+                warn!("The image of cover {series} {n} is missing from the {} store", self.store.name());
+                if cover.source == CoverSource::Manual {
+                    // Downloading another one would replace what the admin chose
+                    Err(CouldNotFindCoverImage("manual cover missing from the store".into(), cover.number))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn stored(&self) -> bool { true }
+}
+
+#[async_trait]
+impl CoverProvider for PerryPedia {
+    fn name(&self) -> &'static str { "perrypedia" }
+
+    async fn find_cover(&self, series: Series, n: u32) -> Result<Option<FoundCover>, Error> {
+        let Some(url) = CoverFinder::find_cover_url(self, series, n, CoverSize::Full).await else {
+            return Ok(None);
+        };
+        match self.download(&url, MAX_COVER_BYTES).await {
+            Ok(image) => {
+                info!("Downloaded cover {series} {n} from {url} ({} bytes)", image.len());
+                Ok(Some(FoundCover { image, url: Some(url), source: CoverSource::PerryPedia, stored: false }))
+            }
+            Err(DownloadError::TooLarge) => { Err(CoverTooLarge(MAX_COVER_BYTES, n as i32)) }
+            Err(DownloadError::Failed(e)) => { Err(CouldNotFindCoverImage(e, n as i32)) }
+        }
+    }
+
+    async fn find_cover_url(&self, series: Series, n: u32) -> Option<String> {
+        CoverFinder::find_cover_url(self, series, n, CoverSize::Full).await
+    }
+}
+
+/// Scans kept on disk: `{dir}/PR/12.jpg`, also `.jpeg`, `.png` or `.webp`
+pub struct DirectoryCoverProvider {
+    pub dir: String,
+}
+
+#[async_trait]
+impl CoverProvider for DirectoryCoverProvider {
+    fn name(&self) -> &'static str { "directory" }
+
+    async fn find_cover(&self, series: Series, n: u32) -> Result<Option<FoundCover>, Error> {
+        for extension in ["jpg", "jpeg", "png", "webp"] {
+            let path = format!("{}/{}/{n}.{extension}", self.dir, series.code());
+            match tokio::fs::read(&path).await {
+                Ok(image) => {
+                    info!("Found cover {series} {n} in {path}");
+                    return Ok(Some(FoundCover { image, url: None, source: CoverSource::Directory, stored: false }));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => { return Err(CouldNotFindCoverImage(format!("{path}: {e}"), n as i32)); }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A cover showing the number and the cycle of the book, for the books nobody has a cover for.
+/// It's never stored.
+pub struct PlaceholderCoverProvider {
+    pub db: Arc<Box<dyn Db>>,
+}
+
+#[async_trait]
+impl CoverProvider for PlaceholderCoverProvider {
+    fn name(&self) -> &'static str { "placeholder" }
+
+    async fn find_cover(&self, series: Series, n: u32) -> Result<Option<FoundCover>, Error> {
+        let cycle = self.db.find_cycle_by_book(series, n).await
+            .map(|c| if c.english_title.is_empty() { c.german_title } else { c.english_title });
+        let mut cursor = Cursor::new(Vec::new());
+        draw_placeholder(series, n, cycle.as_deref()).write_to(&mut cursor, ImageFormat::Png)
+            .map_err(|e| ResizingCover(e.to_string(), n as i32))?;
+        Ok(Some(FoundCover { image: cursor.into_inner(), url: None, source: CoverSource::Placeholder, stored: false }))
+    }
+
+    fn generated(&self) -> bool { true }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use crate::cover_providers::{CoverLookup, CoverProvider, CoverProviderStats, CoverProviders, FoundCover};
+    use crate::entities::{CoverSource, Series};
+    use crate::errors::Error;
+    use crate::errors::Error::UnknownCoverImageError;
+
+    /// Has the covers up to `last`, fails for the others when `fails`
+    struct TestProvider {
+        name: &'static str,
+        last: u32,
+        fails: bool,
+    }
+
+    #[async_trait]
+    impl CoverProvider for TestProvider {
+        fn name(&self) -> &'static str { self.name }
+
+        async fn find_cover(&self, _series: Series, n: u32) -> Result<Option<FoundCover>, Error> {
+            if n <= self.last {
+                Ok(Some(FoundCover { image: vec![n as u8], url: None, source: CoverSource::PerryPedia, stored: false }))
+            } else if self.fails {
+                Err(UnknownCoverImageError(n as i32))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    struct TestPlaceholder;
+
+    #[async_trait]
+    impl CoverProvider for TestPlaceholder {
+        fn name(&self) -> &'static str { "placeholder" }
+
+        async fn find_cover(&self, _series: Series, _n: u32) -> Result<Option<FoundCover>, Error> {
+            Ok(Some(FoundCover { image: Vec::new(), url: None, source: CoverSource::Placeholder, stored: false }))
+        }
+
+        fn generated(&self) -> bool { true }
+    }
+
+    fn stats(name: &'static str, hits: u64, misses: u64, errors: u64) -> CoverProviderStats {
+        CoverProviderStats { name, hits, misses, errors }
+    }
+
+    #[tokio::test]
+    async fn providers_are_asked_in_order() {
+        let providers = CoverProviders::new(vec![
+            Box::new(TestProvider { name: "first", last: 10, fails: false }),
+            Box::new(TestProvider { name: "second", last: 20, fails: true }),
+            Box::new(TestPlaceholder),
+        ]);
+        assert_eq!(providers.find_cover(Series::Pr, 5, CoverLookup::All).await.unwrap().image, vec![5]);
+        assert_eq!(providers.find_cover(Series::Pr, 15, CoverLookup::All).await.unwrap().image, vec![15]);
+        let placeholder = providers.find_cover(Series::Pr, 25, CoverLookup::All).await.unwrap();
+        assert_eq!(placeholder.source, CoverSource::Placeholder);
+        // Without the placeholder, the error of the second provider is what went wrong
+        assert!(matches!(providers.find_cover(Series::Pr, 25, CoverLookup::Found).await, Err(UnknownCoverImageError(25))));
+
+        assert_eq!(providers.stats(), vec![
+            stats("first", 1, 3, 0),
+            stats("second", 1, 0, 2),
+            stats("placeholder", 1, 0, 0),
+        ]);
+    }
+}
@@ -0,0 +1,187 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+use tracing::{info, warn};
+use crate::covers::analyze_cover;
+use crate::entities::{Cover, CoverKey, CoverSize};
+use crate::PerryState;
+
+/// Two perceptual hashes at most that many bits apart show the same picture, re-encoded or
+/// resized
+pub const MAX_DISTANCE: u32 = 3;
+/// How many of the covers stored before the analysis existed the report analyzes each time
+const ANALYZE_BATCH: usize = 100;
+
+/// What the report needs to know about an original
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoverAnalysis {
+    pub width: u32,
+    pub height: u32,
+    pub phash: i64,
+}
+
+pub fn analyze(img: &DynamicImage) -> CoverAnalysis {
+    CoverAnalysis { width: img.width(), height: img.height(), phash: perceptual_hash(img) }
+}
+
+/// dHash: the image shrunk to 9x8 grays, one bit per pair of neighbours telling whether the
+/// left one is brighter. It survives resizing and recompression, unlike the SHA-256.
+pub fn perceptual_hash(img: &DynamicImage) -> i64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash as i64
+}
+
+/// How many bits differ
+pub fn distance(a: i64, b: i64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// The covers that are probably wrong
+#[derive(Debug, Default)]
+pub struct CoverProblems {
+    /// Groups of at least two books whose covers look the same
+    pub duplicates: Vec<Vec<Cover>>,
+    /// Smaller than the box of the summary page in both dimensions
+    pub low_resolution: Vec<Cover>,
+    /// Looking like one of the known placeholders
+    pub placeholders: Vec<Cover>,
+    /// Their original is missing or can't be decoded
+    pub unreadable: Vec<Cover>,
+}
+
+/// Only the analyzed covers are looked at. The ones matching a known placeholder aren't
+/// reported as duplicates too.
+pub fn find_problems(covers: &[Cover], placeholder_hashes: &[i64]) -> CoverProblems {
+    let (min_width, min_height) = CoverSize::Medium.bounds();
+    let mut result = CoverProblems::default();
+    let mut candidates: Vec<(&Cover, i64)> = Vec::new();
+    for cover in covers {
+        let (Some(width), Some(height)) = (cover.width, cover.height) else {
+            continue;
+        };
+        let Some(phash) = cover.phash else {
+            result.unreadable.push(cover.clone());
+            continue;
+        };
+        if (width as u32) < min_width && (height as u32) < min_height {
+            result.low_resolution.push(cover.clone());
+        }
+        if placeholder_hashes.iter().any(|h| distance(*h, phash) <= MAX_DISTANCE) {
+            result.placeholders.push(cover.clone());
+        } else {
+            candidates.push((cover, phash));
+        }
+    }
+
+    let mut grouped = vec![false; candidates.len()];
+    for i in 0..candidates.len() {
+        if grouped[i] {
+            continue;
+        }
+        let mut group = vec![candidates[i].0.clone()];
+        for j in i + 1..candidates.len() {
+            if ! grouped[j] && distance(candidates[i].1, candidates[j].1) <= MAX_DISTANCE {
+                grouped[j] = true;
+                group.push(candidates[j].0.clone());
+            }
+        }
+        if group.len() > 1 {
+            result.duplicates.push(group);
+        }
+    }
+
+    result
+}
+
+/// Analyze some of the covers stored before their dimensions and hash were recorded, updating
+/// `covers` too. The ones that can't be read are recorded as 0x0 without a hash. Returns how
+/// many are left.
+pub async fn analyze_stored_covers(state: &PerryState, covers: &mut [Cover]) -> usize {
+    let mut analyzed = 0;
+    for cover in covers.iter_mut().filter(|c| c.width.is_none()).take(ANALYZE_BATCH) {
+        let (series, number) = (cover.series, cover.number as u32);
+        let analysis = match state.cover_store.get(CoverKey::original(series, cover.number)).await {
+            Some(original) => {
+                analyze_cover(number, original).await
+                    .inspect_err(|e| warn!("Couldn't analyze cover {series} {number}: {e}"))
+                    .ok()
+            }
+            None => {
+                warn!("The original of cover {series} {number} is missing, it can't be analyzed");
+                None
+            }
+        };
+        let (width, height, phash) = match analysis {
+            Some(a) => { (a.width as i32, a.height as i32, Some(a.phash)) }
+            None => { (0, 0, None) }
+        };
+        if state.db.update_cover_analysis(series, number, width, height, phash).await.is_ok() {
+            (cover.width, cover.height, cover.phash) = (Some(width), Some(height), phash);
+            analyzed += 1;
+        }
+    }
+    if analyzed > 0 {
+        info!("Analyzed {analyzed} covers");
+    }
+
+    covers.iter().filter(|c| c.width.is_none()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::imageops::FilterType;
+    use image::{DynamicImage, Rgb, RgbImage};
+    use crate::cover_quality::{distance, find_problems, perceptual_hash, MAX_DISTANCE};
+    use crate::entities::{Cover, CoverSource, Series};
+
+    /// Blocks getting brighter to the right, or to the left when `mirrored`
+    fn blocks(width: u32, height: u32, mirrored: bool) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let x = if mirrored { width - 1 - x } else { x };
+            let v = ((x * 4 / width) * 50 + (y * 3 / height) * 30) as u8;
+            Rgb([v, v, 255 - v])
+        }))
+    }
+
+    fn cover(number: i32, width: i32, height: i32, phash: Option<i64>) -> Cover {
+        Cover { series: Series::Pr, number, url: None, size: 0, hash: None, source: CoverSource::PerryPedia,
+            width: Some(width), height: Some(height), phash }
+    }
+
+    #[test]
+    fn hashes_survive_resizing() {
+        let img = blocks(600, 840, false);
+        let hash = perceptual_hash(&img);
+        let smaller = img.resize(300, 420, FilterType::Lanczos3);
+        assert!(distance(hash, perceptual_hash(&smaller)) <= MAX_DISTANCE);
+        assert!(distance(hash, perceptual_hash(&blocks(600, 840, true))) > MAX_DISTANCE);
+    }
+
+    #[test]
+    fn problems() {
+        let covers = vec![
+            cover(1, 800, 1120, Some(0b1111)),
+            cover(2, 800, 1120, Some(0b0111)),
+            cover(3, 800, 1120, Some(-1)),
+            cover(4, 120, 168, Some(0x5555)),
+            cover(5, 400, 300, Some(0x0f0f_0000)),
+            cover(6, 800, 1120, Some(-2)),
+            cover(7, 800, 1120, None),
+            cover(8, 800, 1120, Some(0b1110)),
+        ];
+        let problems = find_problems(&covers, &[-1]);
+        let numbers = |covers: &[Cover]| covers.iter().map(|c| c.number).collect::<Vec<_>>();
+        assert_eq!(problems.duplicates.iter().map(|g| numbers(g)).collect::<Vec<_>>(), vec![vec![1, 2, 8]]);
+        assert_eq!(numbers(&problems.low_resolution), vec![4]);
+        assert_eq!(numbers(&problems.placeholders), vec![3, 6]);
+        assert_eq!(numbers(&problems.unreadable), vec![7]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::process::exit;
+use std::sync::Arc;
+use async_trait::async_trait;
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use tracing::{error, info};
+use crate::config::Config;
+use crate::db::Db;
+use crate::entities::{CoverKey, Series};
+use crate::errors::Error;
+use crate::errors::Error::{DeletingCover, StoringCoverImage};
+
+/// Where the images of the covers are. Their description (URL, hash, source, dimensions)
+/// always stays in the covers and cover_variants tables.
+#[async_trait]
+pub trait CoverStore: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn get(&self, key: CoverKey) -> Option<Vec<u8>>;
+    async fn put(&self, key: CoverKey, bytes: Vec<u8>) -> Result<(), Error>;
+    /// The original and all the variants of that book
+    async fn delete(&self, series: Series, number: i32) -> Result<(), Error>;
+}
+
+/// COVER_STORE is "postgres" (the default), "filesystem" (in COVER_DIR) or "s3" (S3_BUCKET,
+/// S3_ENDPOINT for MinIO and other S3-compatible services, S3_REGION, S3_ACCESS_KEY_ID and
+/// S3_SECRET_ACCESS_KEY)
+pub fn create_cover_store(config: &Config, db: Arc<Box<dyn Db>>) -> Box<dyn CoverStore> {
+    let store = match config.cover_store.as_str() {
+        "postgres" => { Ok(Box::new(PostgresCoverStore { db }) as Box<dyn CoverStore>) }
+        "filesystem" => {
+            ObjectCoverStore::filesystem(&config.cover_dir).map(|s| Box::new(s) as Box<dyn CoverStore>)
+        }
+        "s3" => { ObjectCoverStore::s3(config).map(|s| Box::new(s) as Box<dyn CoverStore>) }
+        other => { Err(format!("unknown cover store {other}")) }
+    };
+    match store {
+        Ok(store) => {
+            info!("Storing the covers in {}", store.name());
+            store
+        }
+        Err(e) => {
+            error!("Couldn't create the cover store: {e}");
+            exit(1);
+        }
+    }
+}
+
+/// The image columns of the covers and cover_variants tables
+pub struct PostgresCoverStore {
+    pub db: Arc<Box<dyn Db>>,
+}
+
+#[async_trait]
+impl CoverStore for PostgresCoverStore {
+    fn name(&self) -> &'static str { "postgres" }
+
+    async fn get(&self, key: CoverKey) -> Option<Vec<u8>> {
+        self.db.find_cover_image(key).await
+    }
+
+    async fn put(&self, key: CoverKey, bytes: Vec<u8>) -> Result<(), Error> {
+        self.db.update_cover_image(key, Some(bytes)).await
+    }
+
+    /// The images go away with their rows
+    async fn delete(&self, _series: Series, _number: i32) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A directory or an S3-compatible bucket, the images are at CoverKey::path()
+pub struct ObjectCoverStore {
+    name: &'static str,
+    store: Box<dyn ObjectStore>,
+}
+
+impl ObjectCoverStore {
+    /// The errors are only about the configuration, hence strings
+    pub fn filesystem(dir: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{dir}: {e}"))?;
+        let store = LocalFileSystem::new_with_prefix(dir).map_err(|e| e.to_string())?;
+        Ok(ObjectCoverStore { name: "filesystem", store: Box::new(store) })
+    }
+
+    pub fn s3(config: &Config) -> Result<Self, String> {
+        let Some(bucket) = &config.s3_bucket else {
+            return Err("S3_BUCKET is missing".into());
+        };
+        let mut builder = AmazonS3Builder::new()
+            .with_bucket_name(bucket)
+            .with_region(&config.s3_region);
+        if let Some(endpoint) = &config.s3_endpoint {
+            // MinIO serves buckets as paths, usually over plain HTTP
+            builder = builder.with_endpoint(endpoint)
+                .with_virtual_hosted_style_request(false)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let (Some(id), Some(secret)) = (&config.s3_access_key_id, &config.s3_secret_access_key) {
+            builder = builder.with_access_key_id(id).with_secret_access_key(secret);
+        }
+        let store = builder.build().map_err(|e| e.to_string())?;
+        Ok(ObjectCoverStore { name: "s3", store: Box::new(store) })
+    }
+}
+
+#[async_trait]
+impl CoverStore for ObjectCoverStore {
+    fn name(&self) -> &'static str { self.name }
+
+    async fn get(&self, key: CoverKey) -> Option<Vec<u8>> {
+        match self.store.get(&Path::from(key.path())).await {
+            Ok(result) => {
+                match result.bytes().await {
+                    Ok(bytes) => { Some(bytes.to_vec()) }
+                    Err(e) => {
+                        error!("Couldn't read cover {}: {e}", key.path());
+                        None
+                    }
+                }
+            }
+            Err(object_store::Error::NotFound { .. }) => { None }
+            Err(e) => {
+                error!("Couldn't read cover {}: {e}", key.path());
+                None
+            }
+        }
+    }
+
+    async fn put(&self, key: CoverKey, bytes: Vec<u8>) -> Result<(), Error> {
+        self.store.put(&Path::from(key.path()), PutPayload::from(bytes)).await
+            .map(|_| ())
+            .map_err(|e| StoringCoverImage(e.to_string(), key.number))
+    }
+
+    async fn delete(&self, series: Series, number: i32) -> Result<(), Error> {
+        for key in CoverKey::all(series, number) {
+            match self.store.delete(&Path::from(key.path())).await {
+                Ok(_) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => { return Err(DeletingCover(e.to_string(), number)); }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::cover_store::{CoverStore, ObjectCoverStore};
+    use crate::entities::{CoverFormat, CoverKey, CoverSize, Series};
+
+    async fn round_trip(store: &dyn CoverStore) {
+        let original = CoverKey::original(Series::Neo, 12);
+        let thumb = CoverKey::variant(Series::Neo, 12, CoverSize::Thumb, CoverFormat::Webp);
+        store.put(original, vec![1, 2, 3]).await.unwrap();
+        store.put(thumb, vec![4, 5]).await.unwrap();
+        assert_eq!(store.get(original).await, Some(vec![1, 2, 3]));
+        assert_eq!(store.get(thumb).await, Some(vec![4, 5]));
+
+        store.delete(Series::Neo, 12).await.unwrap();
+        assert_eq!(store.get(original).await, None);
+        assert_eq!(store.get(thumb).await, None);
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(CoverKey::original(Series::Pr, 3000).path(), "PR/3000/original");
+        assert_eq!(CoverKey::variant(Series::Neo, 12, CoverSize::Thumb, CoverFormat::Webp).path(),
+            "NEO/12/thumb.webp");
+        assert_eq!(CoverKey::all(Series::Pr, 1).len(), 7);
+    }
+
+    #[tokio::test]
+    async fn filesystem() {
+        let dir = std::env::temp_dir().join(format!("perry-covers-{}", std::process::id()));
+        let store = ObjectCoverStore::filesystem(dir.to_str().unwrap()).unwrap();
+        round_trip(&store).await;
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Needs a bucket, e.g. a local MinIO:
+    /// `S3_BUCKET=covers S3_ENDPOINT=http://localhost:9000 S3_ACCESS_KEY_ID=minioadmin
+    /// S3_SECRET_ACCESS_KEY=minioadmin cargo test s3 -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn s3() {
+        let env = |name: &str| std::env::var(name).ok();
+        let config = Config {
+            s3_bucket: env("S3_BUCKET"),
+            s3_endpoint: env("S3_ENDPOINT"),
+            s3_region: env("S3_REGION").unwrap_or("us-east-1".into()),
+            s3_access_key_id: env("S3_ACCESS_KEY_ID"),
+            s3_secret_access_key: env("S3_SECRET_ACCESS_KEY"),
+            ..Config::default()
+        };
+        round_trip(&ObjectCoverStore::s3(&config).unwrap()).await;
+    }
+}
@@ -1,145 +1,528 @@
-use std::io::{Cursor};
-use std::sync::Arc;
-use std::time::Duration;
+use image::codecs::jpeg::JpegEncoder;
 use image::imageops::FilterType;
-use image::{ImageFormat, load_from_memory};
-use tokio::time::timeout;
+use std::io::Cursor;
+use image::{DynamicImage, ImageError, ImageFormat, ImageReader, ImageResult, Limits, RgbImage};
+use serde::Deserialize;
 use tracing::{debug, error, info, warn};
-use crate::db::Db;
-use crate::errors::Error::{CouldNotFindCoverImage, PerryPediaCouldNotFind, UnknownCoverImageError};
-use crate::errors::{OkContent, PrResult, PrResultBuilder};
-use crate::perrypedia::{CoverFinder, PerryPedia, TIMEOUT_MS};
+use sha2::{Digest, Sha256};
+use crate::entities::{Cover, CoverFormat, CoverKey, CoverSize, CoverSource, CoverVariant, MissingCover, Series};
+use crate::cover_providers::{CoverLookup, FoundCover};
+use crate::cover_quality::{analyze, CoverAnalysis};
+use crate::errors::Error::{CorruptCoverImage, CoverDimensions, CoverNotAnImage, CoverNotFound, CoverTooLarge, ResizingCover};
+use crate::errors::{Error, PrResult, PrResultBuilder};
 use crate::{CookieManager, PerryState};
+use crate::url::Urls;
+
+/// "/covers/12/delete?manual=true"
+#[derive(Deserialize)]
+pub struct DeleteCoverQueryParams {
+    /// Covers uploaded by an admin are only deleted when asked explicitly
+    #[serde(default)]
+    pub manual: bool,
+}
 
 pub async fn delete_cover_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
-        book_number: u32) -> PrResult
+        series: Series, book_number: u32, params: DeleteCoverQueryParams) -> PrResult
 {
-    if cookie_manager.find_user(state.db.clone()).await.is_some() {
-        match state.db.delete_cover(book_number).await {
-            Ok(_) => {
-                info!("Successfully deleted cover {}", book_number);
-            }
-            Err(e) => {
-                error!("Couldn't delete cover {book_number}: {e}");
-            }
+    if ! cookie_manager.find_user(state.db.clone()).await.is_some_and(|u| u.is_admin()) {
+        warn!("Not deleting cover {series} {book_number}: not an admin");
+        return PrResultBuilder::root();
+    }
+
+    let source = state.db.find_cover(series, book_number).await.map(|c| c.source);
+    if source == Some(CoverSource::Manual) && ! params.manual {
+        warn!("Not deleting cover {series} {book_number}: it was uploaded, add ?manual=true to delete it anyway");
+    } else {
+        delete_cover(state, series, book_number).await;
+    }
+
+    PrResultBuilder::redirect(Urls::cover(series, book_number as i32))
+}
+
+/// The images, then the rows. The cover is downloaded again the next time it's needed.
+pub async fn delete_cover(state: &PerryState, series: Series, book_number: u32) {
+    let result = match state.cover_store.delete(series, book_number as i32).await {
+        Ok(()) => { state.db.delete_cover(series, book_number).await }
+        Err(e) => { Err(e) }
+    };
+    match result {
+        Ok(_) => {
+            info!("Successfully deleted cover {}", book_number);
+        }
+        Err(e) => {
+            error!("Couldn't delete cover {book_number}: {e}");
         }
     }
+}
 
-    PrResultBuilder::redirect(format!("/covers/{book_number}"))
+/// "/covers/12?size=thumb&format=webp"
+#[derive(Deserialize)]
+pub struct CoverQueryParams {
+    #[serde(default)]
+    pub size: CoverSize,
+    pub format: Option<CoverFormat>,
 }
 
-pub async fn cover_logic(state: &PerryState, book_number: u32) -> PrResult {
-    let bytes = match find_cover_image(book_number, &state.db).await {
-        Ok(OkContent::Image(bytes)) => {
-            debug!("Returning cover image for book {}, size {} bytes", book_number, bytes.len());
-            bytes
+/// `format` comes from the query string ("?format=webp"), or from the `Accept` header of the
+/// browser when it's absent. `if_none_match` is the `If-None-Match` header.
+pub async fn cover_logic(state: &PerryState, series: Series, book_number: u32, size: CoverSize,
+        format: CoverFormat, if_none_match: Option<String>) -> PrResult
+{
+    // Most requests come from browsers that already have the cover: answer them with the
+    // hash alone, without reading the image
+    let etag = state.db.find_cover_hash(series, book_number).await
+        .map(|hash| cover_etag(&hash, size, format));
+    if let Some(etag) = &etag {
+        if if_none_match.is_some_and(|h| etag_matches(&h, etag)) {
+            debug!("Cover for book {book_number} not modified");
+            return PrResultBuilder::not_modified(etag.clone());
+        }
+    }
+
+    let key = CoverKey::variant(series, book_number as i32, size, format);
+    if let Some(image) = state.cover_store.get(key).await {
+        debug!("Returning {} cover for book {book_number}, size {} bytes", size.name(), image.len());
+        return match etag {
+            Some(etag) => { PrResultBuilder::cached_image(image, etag) }
+            None => { PrResultBuilder::image(image) }
+        };
+    }
+
+    if state.db.find_book(series, book_number).await.is_none() {
+        return PrResultBuilder::not_found();
+    }
+
+    let gave_up = state.db.find_cover_fetch_failure(series, book_number).await.is_some_and(|f| f.permanent);
+    let lookup = if gave_up { CoverLookup::Generated } else { CoverLookup::All };
+    let result = match find_cover_image(state, series, book_number, lookup).await {
+        Err(e) if e.is_permanent() => {
+            warn!("Giving up on the cover of {series} {book_number}: {e}");
+            let _ = state.db.record_cover_fetch_failure(series, book_number, e.to_string(), true).await;
+            find_cover_image(state, series, book_number, CoverLookup::Generated).await
         }
+        result => { result }
+    };
+    let (found, variants) = match result {
+        Ok(result) => { result }
         Err(e) => {
             error!("Couldn't fetch cover: {e}");
-            Vec::new()
+            return PrResultBuilder::not_found();
         }
-        _ => {
-            error!("Unknown error while fetching cover for {book_number}");
-            Vec::new()
+    };
+    let variants = if found.source == CoverSource::Placeholder {
+        // Only the variant that was asked for, it won't be stored
+        let image = found.image.clone();
+        match blocking(book_number, move || create_variant(series, book_number, &image, size, format)).await {
+            Ok(variant) => { vec![variant] }
+            Err(e) => {
+                error!("{e}");
+                return PrResultBuilder::not_found();
+            }
         }
+    } else {
+        variants
     };
+    let bytes = variants.iter()
+        .find(|v| v.key() == key)
+        .map(|v| v.image.clone())
+        .unwrap_or_default();
+    debug!("Returning {} cover for book {book_number}, size {} bytes", size.name(), bytes.len());
+    if found.source == CoverSource::Placeholder {
+        // Keep asking the providers until one of them has the real cover
+        return PrResultBuilder::placeholder_image(bytes);
+    }
+    if let Err(e) = save_variants(state, variants).await {
+        error!("Couldn't store the resized covers: {e}");
+    }
 
-    PrResultBuilder::image(bytes)
-}
-
-async fn find_cover_image(book_number: u32, db: &Arc<Box<dyn Db>>) -> PrResult {
-    // Try to get the image from the database
-    match db.find_cover(book_number).await {
-        None => {
-            fetch_cover_and_insert_into_db(book_number, db).await
-        }
-        Some(cover) => {
-            match &cover.url {
-                Some(url) => {
-                    info!("Found cover URL for {book_number}: {url}");
-                }
-                None => {
-                    info!("No cover URL for {book_number} in database, fetching it");
-                    let perry_pedia = Box::new(PerryPedia);
-                    match perry_pedia.find_cover_url(book_number).await {
-                        Some(url) => {
-                            info!("Found URL: {url}");
-                            db.update_url_for_cover(book_number, url).await?;
-                        }
-                        None => {
-                            warn!("Found no URL");
-                        }
-                    }
-                }
-            }
+    // The cover might have just been downloaded, it has a hash now
+    match state.db.find_cover_hash(series, book_number).await {
+        Some(hash) if ! bytes.is_empty() => {
+            PrResultBuilder::cached_image(bytes, cover_etag(&hash, size, format))
+        }
+        _ => {
+            PrResultBuilder::image(bytes)
+        }
+    }
+}
+
+/// Download a missing cover, or the missing URL of a cover, ahead of the first visitor, and
+/// resize it right away
+pub async fn prefetch_cover(state: &PerryState, missing: &MissingCover) -> Result<(), Error> {
+    let (series, book_number) = (missing.series, missing.number as u32);
+    if missing.has_image {
+        match state.cover_providers.find_cover_url(series, book_number).await {
+            Some(url) => { state.db.update_url_for_cover(series, book_number, url).await }
+            None => { Err(CoverNotFound(missing.number)) }
+        }
+    } else {
+        let (_, variants) = find_cover_image(state, series, book_number, CoverLookup::Found).await?;
+        save_variants(state, variants).await
+    }
+}
+
+/// Larger originals are refused, whether they are downloaded or uploaded
+pub const MAX_COVER_BYTES: usize = 10 * 1024 * 1024;
+/// Smaller covers are icons or broken images
+const MIN_COVER_SIDE: u32 = 50;
+/// Larger ones would take too much memory to decode
+const MAX_COVER_SIDE: u32 = 10_000;
+
+/// Replace the cover of a book with an image supplied by an admin, which downloads won't replace
+pub async fn store_manual_cover(state: &PerryState, series: Series, book_number: u32, bytes: Vec<u8>)
+    -> Result<(), Error>
+{
+    let (analysis, variants) = ingest_cover(series, book_number, bytes.clone()).await?;
+    save_original(state, series, book_number, None, bytes, CoverSource::Manual, analysis).await?;
+    save_variants(state, variants).await?;
+    let _ = state.db.delete_cover_fetch_failure(series, book_number).await;
+    info!("Stored the manual cover of {series} {book_number}");
+
+    Ok(())
+}
 
-            PrResultBuilder::image(cover.image)
+/// Replace a cover that looks wrong with the one the providers find now, ignoring the stored one.
+/// The wrong cover stays when nothing else is found.
+pub async fn refetch_cover(state: &PerryState, series: Series, book_number: u32) -> Result<(), Error> {
+    let result = async {
+        let found = state.cover_providers.find_cover(series, book_number, CoverLookup::Fresh).await?;
+        let (analysis, variants) = ingest_cover(series, book_number, found.image.clone()).await?;
+        // A manual cover would refuse to be replaced by a download
+        state.cover_store.delete(series, book_number as i32).await?;
+        state.db.delete_cover(series, book_number).await?;
+        save_original(state, series, book_number, found.url, found.image, found.source, analysis).await?;
+        save_variants(state, variants).await
+    }.await;
+    match &result {
+        Ok(()) => {
+            info!("Refetched the cover of {series} {book_number}");
+            let _ = state.db.delete_cover_fetch_failure(series, book_number).await;
         }
+        Err(e) => {
+            warn!("Couldn't refetch the cover of {series} {book_number}: {e}");
+            let _ = state.db.record_cover_fetch_failure(series, book_number, e.to_string(), e.is_permanent()).await;
+        }
+    }
+    result
+}
+
+/// The row goes to the database, the image to the CoverStore
+#[allow(clippy::too_many_arguments)]
+async fn save_original(state: &PerryState, series: Series, book_number: u32, url: Option<String>,
+        bytes: Vec<u8>, source: CoverSource, analysis: CoverAnalysis)
+    -> Result<(), Error>
+{
+    let cover = Cover {
+        series,
+        number: book_number as i32,
+        url,
+        size: bytes.len() as i32,
+        hash: Some(cover_hash(&bytes)),
+        source,
+        width: Some(analysis.width as i32),
+        height: Some(analysis.height as i32),
+        phash: Some(analysis.phash),
+    };
+    state.db.insert_cover(cover).await?;
+    state.cover_store.put(CoverKey::original(series, book_number as i32), bytes).await
+}
+
+async fn save_variants(state: &PerryState, variants: Vec<CoverVariant>) -> Result<(), Error> {
+    state.db.insert_cover_variants(variants.clone()).await?;
+    for v in variants {
+        state.cover_store.put(v.key(), v.image).await?;
+    }
+    Ok(())
+}
+
+/// SHA-256 of the original, in hexadecimal
+pub fn cover_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// All the variants derive from the original, so its hash identifies them too
+fn cover_etag(hash: &str, size: CoverSize, format: CoverFormat) -> String {
+    format!("\"{hash}-{}-{}\"", size.name(), format.name())
+}
+
+/// Whether the value of an `If-None-Match` header ("*", or a list of possibly weak ETags)
+/// matches that ETag
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.strip_prefix("W/").unwrap_or(t) == etag)
+}
+
+/// An explicit `?format=` wins, otherwise WebP is only sent to clients that say they accept it
+pub fn negotiate_format(format: Option<CoverFormat>, accept: Option<&str>) -> CoverFormat {
+    format.unwrap_or_else(|| {
+        if accept.is_some_and(|a| a.contains("image/webp")) { CoverFormat::Webp } else { CoverFormat::Jpeg }
+    })
+}
+
+/// What the bytes actually are, whatever the URL or the column they came from says
+pub fn content_type(bytes: &[u8]) -> &'static str {
+    match image::guess_format(bytes) {
+        Ok(format) => { format.to_mime_type() }
+        Err(_) => { "application/octet-stream" }
     }
 }
 
-async fn fetch_cover_and_insert_into_db(book_number: u32, db: &Arc<Box<dyn Db>>)
-    -> PrResult
+/// Ask the cover providers for the original, check it and resize it, and save the original
+/// when it's new. The variants are left to the caller, there are none for the placeholders.
+async fn find_cover_image(state: &PerryState, series: Series, book_number: u32, lookup: CoverLookup)
+    -> Result<(FoundCover, Vec<CoverVariant>), Error>
 {
-    info!("Couldn't find cover for {book_number} in database, fetching it");
-    let perry_pedia = Box::new(PerryPedia);
-    match perry_pedia.find_cover_url(book_number).await {
-        None => {
-            Err(PerryPediaCouldNotFind(book_number as i32))
-        }
-        Some(url) => {
-            let url2 = url.clone();
-            match timeout(Duration::from_millis(TIMEOUT_MS), reqwest::get(url)).await {
-                Ok(Ok(response)) => {
-                    match response.bytes().await {
-                        Ok(bytes) => {
-                            let len = bytes.len();
-                            let new_bytes = resize_image(&bytes, 400, 300);
-                            info!("Found cover for {book_number} at {url2}, \
-                                        inserting it into the database after shrinking it \
-                                         from {} to {} bytes", len, new_bytes.len());
-                            db.insert_cover(book_number, url2.clone(), new_bytes.clone()).await?;
-                            PrResultBuilder::image(new_bytes)
-                        }
-                        Err(e) => {
-                            Err(CouldNotFindCoverImage(e.to_string(), book_number as i32))
-                        }
-                    }
-                }
-                Err(e) => {
-                    Err(CouldNotFindCoverImage(e.to_string(), book_number as i32))
-                }
-                _ => {
-                    Err(UnknownCoverImageError(book_number as i32))
-                }
+    let found = state.cover_providers.find_cover(series, book_number, lookup).await?;
+    if found.source == CoverSource::Placeholder {
+        info!("No cover for {series} {book_number}, using a placeholder");
+        return Ok((found, Vec::new()));
+    }
+
+    let (analysis, variants) = ingest_cover(series, book_number, found.image.clone()).await?;
+    if ! found.stored {
+        info!("Inserting the original of cover {series} {book_number} ({} bytes)", found.image.len());
+        save_original(state, series, book_number, found.url.clone(), found.image.clone(), found.source,
+            analysis).await?;
+    } else if found.url.is_none() && found.source == CoverSource::PerryPedia {
+        info!("No cover URL for {book_number} in database, fetching it");
+        match state.cover_providers.find_cover_url(series, book_number).await {
+            Some(url) => {
+                info!("Found URL: {url}");
+                state.db.update_url_for_cover(series, book_number, url).await?;
+            }
+            None => {
+                warn!("Found no URL");
             }
         }
     }
+
+    Ok((found, variants))
 }
 
-fn resize_image(bytes: &[u8], target_width: u32, target_height: u32) -> Vec<u8> {
-    let img = load_from_memory(bytes.into()).unwrap();
+/// Check that an original can be a cover, analyze it and create all its variants. Decoding,
+/// resizing and encoding take long enough to stall the other requests, they run on the blocking pool.
+pub async fn ingest_cover(series: Series, book_number: u32, original: Vec<u8>)
+    -> Result<(CoverAnalysis, Vec<CoverVariant>), Error>
+{
+    blocking(book_number, move || {
+        let img = decode(book_number, &original)?;
+        Ok((analyze(&img), all_variants(series, book_number, &img)?))
+    }).await
+}
 
-    let aspect_ratio = img.width() as f32 / img.height() as f32;
+/// For the covers stored before they were analyzed, which weren't checked as closely: the tiny
+/// ones are analyzed too, the report flags them
+pub async fn analyze_cover(book_number: u32, original: Vec<u8>) -> Result<CoverAnalysis, Error> {
+    blocking(book_number, move || decode_with_min_side(book_number, &original, 1).map(|img| analyze(&img))).await
+}
 
-    // Calculate new dimensions maintaining aspect ratio
-    let (new_width, new_height) = if (target_width as f32 / target_height as f32) > aspect_ratio {
-        let new_height = target_height;
-        let new_width = (new_height as f32 * aspect_ratio) as u32;
-        (new_width, new_height)
-    } else {
-        let new_width = target_width;
-        let new_height = (new_width as f32 / aspect_ratio) as u32;
-        (new_width, new_height)
+async fn blocking<T: Send + 'static>(book_number: u32, f: impl FnOnce() -> Result<T, Error> + Send + 'static)
+    -> Result<T, Error>
+{
+    tokio::task::spawn_blocking(f).await
+        .unwrap_or_else(|e| Err(ResizingCover(e.to_string(), book_number as i32)))
+}
+
+/// Every (size, format) combination of that original
+fn all_variants(series: Series, book_number: u32, img: &DynamicImage) -> Result<Vec<CoverVariant>, Error> {
+    let mut result = Vec::new();
+    for size in CoverSize::ALL {
+        let resized = resize(img, size);
+        for format in CoverFormat::ALL {
+            result.push(variant(series, book_number, &resized, size, format)?);
+        }
+    }
+
+    Ok(result)
+}
+
+/// A single variant of that original
+pub fn create_variant(series: Series, book_number: u32, original: &[u8], size: CoverSize, format: CoverFormat)
+    -> Result<CoverVariant, Error>
+{
+    let img = decode(book_number, original)?;
+    variant(series, book_number, &resize(&img, size), size, format)
+}
+
+fn decode(book_number: u32, original: &[u8]) -> Result<DynamicImage, Error> {
+    decode_with_min_side(book_number, original, MIN_COVER_SIDE)
+}
+
+/// The checks happen before anything is decoded: the size, the magic bytes, then the dimensions
+/// read from the header
+pub fn decode_with_min_side(book_number: u32, original: &[u8], min_side: u32) -> Result<DynamicImage, Error> {
+    let n = book_number as i32;
+    if original.len() > MAX_COVER_BYTES {
+        return Err(CoverTooLarge(MAX_COVER_BYTES, n));
+    }
+    let format = match image::guess_format(original) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif)) => { format }
+        Ok(format) => { return Err(CoverNotAnImage(format!("{format:?} isn't supported"), n)); }
+        Err(_) => { return Err(CoverNotAnImage(describe(original), n)); }
     };
+    let reader = || {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_COVER_SIDE);
+        limits.max_image_height = Some(MAX_COVER_SIDE);
+        let mut reader = ImageReader::with_format(Cursor::new(original), format);
+        reader.limits(limits);
+        reader
+    };
+    let (width, height) = reader().into_dimensions().map_err(|e| match e {
+        ImageError::Limits(_) => { CoverDimensions(0, 0, n) }
+        e => { CorruptCoverImage(e.to_string(), n) }
+    })?;
+    if width < min_side || height < min_side || width > MAX_COVER_SIDE || height > MAX_COVER_SIDE {
+        return Err(CoverDimensions(width, height, n));
+    }
+    reader().decode().map_err(|e| match e {
+        ImageError::Limits(_) => { CoverDimensions(width, height, n) }
+        e => { CorruptCoverImage(e.to_string(), n) }
+    })
+}
+
+/// What bytes that aren't an image look like, PerryPedia sometimes answers with a page
+fn describe(bytes: &[u8]) -> String {
+    let start = String::from_utf8_lossy(&bytes[..bytes.len().min(100)]).trim_start().to_lowercase();
+    if bytes.is_empty() {
+        "the file is empty".into()
+    } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "it's an HTML page".into()
+    } else if start.starts_with('<') {
+        "it's an XML or HTML document".into()
+    } else {
+        "unknown format".into()
+    }
+}
+
+fn resize(img: &DynamicImage, size: CoverSize) -> RgbImage {
+    let (width, height) = size.bounds();
+    // resize() keeps the aspect ratio, it only has to be prevented from enlarging the image
+    if img.width() <= width && img.height() <= height {
+        img.to_rgb8()
+    } else {
+        img.resize(width, height, FilterType::Lanczos3).to_rgb8()
+    }
+}
+
+fn variant(series: Series, book_number: u32, resized: &RgbImage, size: CoverSize, format: CoverFormat)
+    -> Result<CoverVariant, Error>
+{
+    let image = encode(resized, format).map_err(|e| ResizingCover(e.to_string(), book_number as i32))?;
+    Ok(CoverVariant {
+        series,
+        number: book_number as i32,
+        size,
+        format,
+        width: resized.width() as i32,
+        height: resized.height() as i32,
+        image,
+    })
+}
+
+pub fn encode(image: &RgbImage, format: CoverFormat) -> ImageResult<Vec<u8>> {
+    let mut result: Vec<u8> = Vec::new();
+    match format {
+        CoverFormat::Jpeg => {
+            image.write_with_encoder(JpegEncoder::new_with_quality(&mut result, 85))?;
+        }
+        CoverFormat::Webp => {
+            // The image crate only encodes lossless WebP, several times larger than the JPEG
+            let webp = webp::Encoder::from_rgb(image.as_raw(), image.width(), image.height());
+            result.extend_from_slice(&webp.encode(80.0));
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+    use crate::covers::{all_variants, content_type, decode, etag_matches, ingest_cover, negotiate_format};
+    use crate::entities::{CoverFormat, CoverSize, CoverVariant, Series};
+    use crate::errors::Error::{CorruptCoverImage, CoverDimensions, CoverNotAnImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbImage::from_fn(width, height, |x, y| Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        let mut cursor = Cursor::new(Vec::new());
+        img.write_to(&mut cursor, ImageFormat::Png).unwrap();
+        cursor.into_inner()
+    }
+
+    fn create_variants(original: &[u8]) -> Vec<CoverVariant> {
+        all_variants(Series::Pr, 12, &decode(12, original).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn variants_keep_the_aspect_ratio_and_never_enlarge() {
+        let variants = create_variants(&png(1000, 1400));
+        assert_eq!(variants.len(), 6);
+        let dimensions = |size: CoverSize| variants.iter()
+            .find(|v| v.size == size && v.format == CoverFormat::Jpeg)
+            .map(|v| (v.width, v.height)).unwrap();
+        assert_eq!(dimensions(CoverSize::Thumb), (120, 168));
+        assert_eq!(dimensions(CoverSize::Medium), (300, 420));
+        assert_eq!(dimensions(CoverSize::Full), (800, 1120));
+
+        let small = create_variants(&png(100, 140));
+        assert!(small.iter().all(|v| (v.width, v.height) == (100, 140)));
+    }
+
+    #[tokio::test]
+    async fn unusable_images_are_refused() {
+        let html = b"<!DOCTYPE html><html><body>Not found</body></html>".to_vec();
+        assert!(matches!(ingest_cover(Series::Pr, 12, html).await, Err(CoverNotAnImage(e, 12)) if e.contains("HTML")));
+        assert!(matches!(ingest_cover(Series::Pr, 12, Vec::new()).await, Err(CoverNotAnImage(..))));
+        assert!(matches!(ingest_cover(Series::Pr, 12, png(20, 30)).await, Err(CoverDimensions(20, 30, 12))));
+
+        let mut truncated = png(200, 300);
+        truncated.truncate(truncated.len() / 2);
+        let result = ingest_cover(Series::Pr, 12, truncated).await;
+        assert!(matches!(&result, Err(CorruptCoverImage(..))));
+        assert!(result.unwrap_err().is_permanent());
+    }
+
+    #[test]
+    fn webp_is_lossy() {
+        let variants = create_variants(&png(1000, 1400));
+        let size = |format: CoverFormat| variants.iter()
+            .find(|v| v.size == CoverSize::Full && v.format == format)
+            .map(|v| v.image.len()).unwrap();
+        assert!(size(CoverFormat::Webp) < size(CoverFormat::Jpeg));
+    }
 
-    // Resize the image
-    let resized = img.resize(new_width, new_height, FilterType::Gaussian);
+    #[test]
+    fn content_type_is_sniffed() {
+        let variants = create_variants(&png(200, 300));
+        for v in variants {
+            let expected = match v.format {
+                CoverFormat::Jpeg => "image/jpeg",
+                CoverFormat::Webp => "image/webp",
+            };
+            assert_eq!(content_type(&v.image), expected);
+        }
+        assert_eq!(content_type(&png(10, 10)), "image/png");
+        assert_eq!(content_type(b"<html>"), "application/octet-stream");
+    }
 
-    // Convert back to PNG bytes
-    let output_bytes: Vec<u8> = Vec::new();
-    let mut cursor = Cursor::new(output_bytes);
-    resized.write_to(&mut cursor, ImageFormat::Png).unwrap();
+    #[test]
+    fn if_none_match() {
+        let etag = "\"abc-thumb-jpeg\"";
+        assert!(etag_matches("\"abc-thumb-jpeg\"", etag));
+        assert!(etag_matches("\"xyz\", W/\"abc-thumb-jpeg\"", etag));
+        assert!(etag_matches("*", etag));
+        assert!(! etag_matches("\"abc-thumb-webp\"", etag));
+    }
 
-    cursor.into_inner()
-}
\ No newline at end of file
+    #[test]
+    fn format_negotiation() {
+        let chrome = Some("image/avif,image/webp,image/apng,image/*,*/*;q=0.8");
+        assert_eq!(negotiate_format(None, chrome), CoverFormat::Webp);
+        assert_eq!(negotiate_format(None, Some("*/*")), CoverFormat::Jpeg);
+        assert_eq!(negotiate_format(None, None), CoverFormat::Jpeg);
+        assert_eq!(negotiate_format(Some(CoverFormat::Jpeg), chrome), CoverFormat::Jpeg);
+    }
+}
@@ -1,12 +1,13 @@
 use std::time::Instant;
 use async_trait::async_trait;
+use chrono::NaiveDate;
 use sqlx::{Pool, Postgres};
 use sqlx::postgres::{PgPoolOptions};
 use sqlx::Row;
 use tracing::{debug, error, info, warn};
 use crate::config::Config;
-use crate::entities::{Book, Cycle, Cover, PendingSummary, Summary, User};
-use crate::errors::Error::{DeletingCover, FetchingCycles, InsertingBook, InsertingCoverImage, InsertingInPending, InsertingSummary, Unknown, UpdatingBook, UpdatingCoverUrl, UpdatingSummary, UpdatingUser};
+use crate::entities::{Appearance, MissingCover, Book, BookEdit, Comment, CommentStatus, Cycle, CycleProgress, CycleStats, Cover, CoverFetchFailure, CoverKey, CoverStats, CoverVariant, Entity, PendingSummary, PlaceholderHash, Rating, Reading, Series, Summary, User};
+use crate::errors::Error::{DeletingComment, DeletingCover, FetchingCycles, InsertingBook, InsertingComment, InsertingEntity, InsertingCoverImage, InsertingCoverVariants, InsertingInPending, RecordingCoverFailure, InsertingSummary, Unknown, UpdatingBook, UpdatingComment, UpdatingCoverAnalysis, UpdatingCoverUrl, UpdatingPlaceholderHash, UpdatingReading, UpdatingReferences, UpdatingSummary, UpdatingSummaryEntities, UpdatingUser};
 use crate::errors::{DbResult, Error};
 
 pub async fn create_db(config: &Config) -> Box<dyn Db> {
@@ -24,21 +25,48 @@ pub async fn create_db(config: &Config) -> Box<dyn Db> {
 
 #[async_trait]
 pub trait Db: Send + Sync {
-    async fn fetch_cycles(&self) -> DbResult<Vec<Cycle>> { Ok(Vec::new()) }
+    async fn fetch_cycles(&self, _series: Series) -> DbResult<Vec<Cycle>> { Ok(Vec::new()) }
     async fn fetch_users(&self) -> Vec<User> { Vec::new() }
-    async fn find_summary(&self, _number: u32) -> Option<Summary> { None }
-    async fn fetch_summary_count(&self) -> u16 { 4200 }
-    async fn fetch_book_count(&self) -> u16 { 4200 }
-    async fn fetch_most_recent_summaries(&self) -> Vec<Summary> { Vec::new() }
-    async fn find_cycle(&self, _cycle_number: u32) -> DbResult<Cycle> { Err(Unknown("find_cycles() not implemented".into() ))}
-    async fn find_cycle_by_book(&self, _book_number: u32) -> Option<Cycle> { None }
-    async fn find_books(&self, _cycle_number: u32) -> DbResult<Vec<Book>> { Err(Unknown("find_books() not implemented".into() ))}
-    async fn find_summaries(&self, _cycle_number: u32) -> DbResult<Vec<Summary>> { Err(Unknown("find_summaries() not implemented".into() ))}
-    async fn find_book(&self, _book_number: u32) -> Option<Book> { None }
-    async fn find_cover(&self, _book_number: u32) -> Option<Cover> { None }
-    async fn update_url_for_cover(&self, _book_number: u32, _url: String) -> DbResult<()> { Err(Unknown("update_url_for_cover() not implemented".into() ))}
-    async fn delete_cover(&self, _book_number: u32) -> DbResult<()> { Ok(()) }
-    async fn insert_cover(&self, _book_number: u32, _url: String, _bytes: Vec<u8>) -> DbResult<()> { Ok(()) }
+    async fn find_summary(&self, _series: Series, _number: u32) -> Option<Summary> { None }
+    async fn fetch_summary_count(&self, _series: Series) -> u16 { 4200 }
+    async fn fetch_book_count(&self, _series: Series) -> u16 { 4200 }
+    async fn fetch_most_recent_summaries(&self, _series: Series) -> Vec<Summary> { Vec::new() }
+    async fn find_cycle(&self, _series: Series, _cycle_number: u32) -> DbResult<Cycle> { Err(Unknown("find_cycles() not implemented".into() ))}
+    async fn find_cycle_by_book(&self, _series: Series, _book_number: u32) -> Option<Cycle> { None }
+    async fn find_books(&self, _series: Series, _cycle_number: u32) -> DbResult<Vec<Book>> { Err(Unknown("find_books() not implemented".into() ))}
+    async fn find_summaries(&self, _series: Series, _cycle_number: u32) -> DbResult<Vec<Summary>> { Err(Unknown("find_summaries() not implemented".into() ))}
+    async fn find_book(&self, _series: Series, _book_number: u32) -> Option<Book> { None }
+    async fn find_cover(&self, _series: Series, _book_number: u32) -> Option<Cover> { None }
+    /// The hash of the original image, without reading the image
+    async fn find_cover_hash(&self, _series: Series, _book_number: u32) -> Option<String> { None }
+    async fn update_url_for_cover(&self, _series: Series, _book_number: u32, _url: String) -> DbResult<()> { Err(Unknown("update_url_for_cover() not implemented".into() ))}
+    async fn delete_cover(&self, _series: Series, _book_number: u32) -> DbResult<()> { Ok(()) }
+    /// Replaces the existing cover, unless that one was uploaded manually and this one wasn't.
+    /// The image goes to the CoverStore.
+    async fn insert_cover(&self, _cover: Cover) -> DbResult<()> { Ok(()) }
+    /// The rows describing the variants, their images go to the CoverStore
+    async fn insert_cover_variants(&self, _variants: Vec<CoverVariant>) -> DbResult<()> { Ok(()) }
+    /// The image columns used by the Postgres CoverStore
+    async fn find_cover_image(&self, _key: CoverKey) -> Option<Vec<u8>> { None }
+    async fn update_cover_image(&self, _key: CoverKey, _image: Option<Vec<u8>>) -> DbResult<()> { Ok(()) }
+    /// Books of every series that need a cover, skipping the ones whose last failure is too recent
+    async fn find_missing_covers(&self, _limit: i64) -> Vec<MissingCover> { Vec::new() }
+    /// Each failure pushes the next attempt further away, up to 30 days. After a `permanent`
+    /// one, there is no next attempt.
+    async fn record_cover_fetch_failure(&self, _series: Series, _book_number: u32, _error: String,
+        _permanent: bool) -> DbResult<()> { Ok(()) }
+    async fn find_cover_fetch_failure(&self, _series: Series, _book_number: u32) -> Option<CoverFetchFailure> { None }
+    async fn delete_cover_fetch_failure(&self, _series: Series, _book_number: u32) -> DbResult<()> { Ok(()) }
+    async fn find_cover_fetch_failures(&self) -> Vec<CoverFetchFailure> { Vec::new() }
+    async fn fetch_cover_stats(&self) -> Vec<CoverStats> { Vec::new() }
+    /// Every cover of every series, without the images
+    async fn find_covers(&self) -> Vec<Cover> { Vec::new() }
+    /// `phash` is `None` when the image couldn't be decoded
+    async fn update_cover_analysis(&self, _series: Series, _book_number: u32, _width: i32, _height: i32,
+        _phash: Option<i64>) -> DbResult<()> { Ok(()) }
+    async fn find_placeholder_hashes(&self) -> Vec<PlaceholderHash> { Vec::new() }
+    async fn insert_placeholder_hash(&self, _phash: i64, _series: Series, _book_number: u32) -> DbResult<()> { Ok(()) }
+    async fn delete_placeholder_hash(&self, _phash: i64) -> DbResult<()> { Ok(()) }
     async fn insert_summary(&self, _summary: Summary) -> DbResult<()> { Ok(()) }
     async fn update_summary(&self, _summary: Summary) -> DbResult<()> { Ok(()) }
     async fn update_or_insert_book(&self, _book: Book) -> DbResult<()> { Ok(()) }
@@ -50,6 +78,47 @@ pub trait Db: Send + Sync {
         -> DbResult<()> { Ok(()) }
     async fn find_pending_summaries(&self) -> Vec<PendingSummary> { Vec::new() }
     async fn insert_cycle(&self, _cycle: Cycle) -> DbResult<()> { Ok(()) }
+    /// Replace the cycle currently numbered `number` in the cycle's series, which allows renumbering it
+    async fn update_cycle(&self, _number: i32, _cycle: Cycle) -> DbResult<()> { Ok(()) }
+    async fn delete_cycle(&self, _series: Series, _number: i32) -> DbResult<()> { Ok(()) }
+    /// Save a book edited by an editor along with the record of the changes, all or nothing
+    async fn update_book_with_edits(&self, _book: Book, _edits: Vec<BookEdit>) -> DbResult<()> { Ok(()) }
+    /// The most recent changes made to the books of that series, or to only one of them
+    async fn find_book_edits(&self, _series: Series, _book_number: Option<u32>) -> Vec<BookEdit> { Vec::new() }
+    /// Books of that series published between `start` and `end` (inclusive), oldest first
+    async fn find_books_published_between(&self, _series: Series, _start: NaiveDate, _end: NaiveDate)
+        -> Vec<Book> { Vec::new() }
+    /// Summary coverage of every cycle of that series
+    async fn fetch_cycle_stats(&self, _series: Series) -> Vec<CycleStats> { Vec::new() }
+    /// The `hefte` rows that don't belong to any cycle
+    async fn find_books_outside_cycles(&self, _series: Series) -> Vec<Book> { Vec::new() }
+    async fn find_comments(&self, _series: Series, _book_number: u32, _status: CommentStatus) -> Vec<Comment> { Vec::new() }
+    async fn find_comments_by_status(&self, _status: CommentStatus) -> Vec<Comment> { Vec::new() }
+    async fn find_comment(&self, _id: i32) -> Option<Comment> { None }
+    async fn find_comment_by_token(&self, _token: &str) -> Option<Comment> { None }
+    async fn insert_comment(&self, _comment: Comment) -> DbResult<()> { Ok(()) }
+    async fn update_comment_status(&self, _id: i32, _status: CommentStatus) -> DbResult<()> { Ok(()) }
+    async fn delete_comment(&self, _id: i32) -> DbResult<()> { Ok(()) }
+    async fn find_reading(&self, _login: &str, _series: Series, _book_number: u32) -> Option<Reading> { None }
+    async fn update_reading(&self, _reading: Reading) -> DbResult<()> { Ok(()) }
+    /// Aggregated ratings of the books between `start` and `end` (inclusive)
+    async fn find_ratings(&self, _series: Series, _start: i32, _end: i32) -> Vec<Rating> { Vec::new() }
+    async fn fetch_reading_progress(&self, _login: &str, _series: Series) -> Vec<CycleProgress> { Vec::new() }
+    async fn fetch_entities(&self) -> Vec<Entity> { Vec::new() }
+    async fn find_entity(&self, _slug: &str) -> Option<Entity> { None }
+    async fn insert_entity(&self, _entity: Entity) -> DbResult<()> { Ok(()) }
+    async fn find_summary_entities(&self, _series: Series, _book_number: u32) -> Vec<Entity> { Vec::new() }
+    async fn update_summary_entities(&self, _series: Series, _book_number: u32, _entity_ids: Vec<i32>) -> DbResult<()> { Ok(()) }
+    /// The summaries of every series tagged with that entity, in publication order
+    async fn find_appearances(&self, _entity_id: i32) -> Vec<Appearance> { Vec::new() }
+    /// The summaries of every series
+    async fn fetch_all_summaries(&self) -> Vec<Summary> { Vec::new() }
+    /// Which of these books of that series have a summary
+    async fn find_summary_numbers(&self, _series: Series, _numbers: Vec<u32>) -> Vec<u32> { Vec::new() }
+    /// Replace the books referenced by the summary `from`, which are in the same series
+    async fn update_references(&self, _series: Series, _from: u32, _to: Vec<u32>) -> DbResult<()> { Ok(()) }
+    /// The summaries referencing that book
+    async fn find_backlinks(&self, _series: Series, _book_number: u32) -> Vec<Appearance> { Vec::new() }
 }
 
 #[derive(Clone)]
@@ -78,6 +147,17 @@ async fn find_user_by(pool: &Pool<Postgres>, key: &str, value: &str) -> Option<U
     }
 }
 
+/// Entities with their aliases, `clause` adds joins and conditions
+fn entities_query(clause: &str) -> String {
+    format!("select e.id, e.slug, e.name, e.kind, \
+            coalesce(array_agg(a.alias order by a.alias) filter (where a.alias is not null), '{{}}')::text[] \
+                as aliases \
+        from entities e left join entity_aliases a on a.entity_id = e.id \
+        {clause} \
+        group by e.id \
+        order by e.name")
+}
+
 #[derive(Clone, Copy, Default)]
 pub struct DbInMemory;
 
@@ -127,8 +207,9 @@ impl DbPostgres {
         }
     }
 
-    async fn fetch_count(&self, table: &str) -> u16 {
-        let result = match sqlx::query(&format!("SELECT COUNT(*) FROM {table}"))
+    async fn fetch_count(&self, table: &str, series: Series) -> u16 {
+        let result = match sqlx::query(&format!("SELECT COUNT(*) FROM {table} where series = $1"))
+            .bind(series)
             .fetch_one(&self.pool)
             .await
         {
@@ -162,9 +243,10 @@ impl DbPostgres {
 
 #[async_trait]
 impl Db for DbPostgres {
-    async fn fetch_cycles(&self) -> DbResult<Vec<Cycle>> {
+    async fn fetch_cycles(&self, series: Series) -> DbResult<Vec<Cycle>> {
         match sqlx::query_as::<_, Cycle>(
-            "select * from cycles order by number desc")
+            "select * from cycles where series = $1 order by number desc")
+            .bind(series)
             .fetch_all(&self.pool)
             .await
         {
@@ -178,9 +260,10 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn find_summary(&self, number: u32) -> Option<Summary> {
+    async fn find_summary(&self, series: Series, number: u32) -> Option<Summary> {
         let start = Instant::now();
-        let s = sqlx::query_as::<_, Summary>("SELECT * FROM SUMMARIES where number = $1")
+        let s = sqlx::query_as::<_, Summary>("SELECT * FROM SUMMARIES where series = $1 and number = $2")
+            .bind(series)
             .bind(number as i32)
             .fetch_optional(&self.pool)
             .await;
@@ -196,18 +279,19 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn fetch_summary_count(&self) -> u16 {
-        self.fetch_count("summaries").await
+    async fn fetch_summary_count(&self, series: Series) -> u16 {
+        self.fetch_count("summaries", series).await
     }
 
-    async fn fetch_book_count(&self) -> u16 {
-        self.fetch_count("hefte").await
+    async fn fetch_book_count(&self, series: Series) -> u16 {
+        self.fetch_count("hefte", series).await
     }
 
-    async fn fetch_most_recent_summaries(&self) -> Vec<Summary> {
+    async fn fetch_most_recent_summaries(&self, series: Series) -> Vec<Summary> {
         let mut result = Vec::new();
         match sqlx::query_as::<_, Summary>(
-            "select * from (select * from summaries where date != '') order by date desc limit 5")
+            "select * from (select * from summaries where date != '' and series = $1) order by date desc limit 5")
+            .bind(series)
             .fetch_all(&self.pool)
             .await
         {
@@ -222,9 +306,10 @@ impl Db for DbPostgres {
         result
     }
 
-    async fn find_cycle(&self, number: u32) -> DbResult<Cycle> {
+    async fn find_cycle(&self, series: Series, number: u32) -> DbResult<Cycle> {
         match sqlx::query_as::<_, Cycle>(
-            "select * from cycles where number = $1")
+            "select * from cycles where series = $1 and number = $2")
+            .bind(series)
             .bind(number as i32)
             .fetch_one(&self.pool)
             .await
@@ -240,11 +325,13 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn find_cycle_by_book(&self, book_number: u32) -> Option<Cycle> {
+    async fn find_cycle_by_book(&self, series: Series, book_number: u32) -> Option<Cycle> {
         let start = Instant::now();
         let book_number = book_number as i32;
         let result = sqlx::query_as::<_, Cycle>(
-            "select * from cycles where $1 between start and \"end\"")
+            "select * from cycles where series = $1 and $2 between start and \"end\" \
+             order by start, number limit 1")
+            .bind(series)
             .bind(book_number)
             .fetch_one(&self.pool)
             .await;
@@ -262,15 +349,16 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn find_books(&self, cycle_number: u32) -> DbResult<Vec<Book>> {
-        match self.find_cycle(cycle_number).await {
+    async fn find_books(&self, series: Series, cycle_number: u32) -> DbResult<Vec<Book>> {
+        match self.find_cycle(series, cycle_number).await {
             Ok(cycle) => {
                 let start = cycle.start;
                 let end = cycle.end;
                 match sqlx::query_as::<_, Book>(
-                    "select * from hefte where number >= $1 and number <= $2")
+                    "select * from hefte where series = $3 and number >= $1 and number <= $2 order by number")
                     .bind(start)
                     .bind(end)
+                    .bind(series)
                     .fetch_all(&self.pool)
                     .await
                 {
@@ -291,15 +379,16 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn find_summaries(&self, cycle_number: u32) -> DbResult<Vec<Summary>> {
-        match self.find_cycle(cycle_number).await {
+    async fn find_summaries(&self, series: Series, cycle_number: u32) -> DbResult<Vec<Summary>> {
+        match self.find_cycle(series, cycle_number).await {
             Ok(cycle) => {
                 let start = cycle.start;
                 let end = cycle.end;
                 match sqlx::query_as::<_, Summary>(
-                    "select * from summaries where number >= $1 and number <= $2")
+                    "select * from summaries where series = $3 and number >= $1 and number <= $2")
                     .bind(start)
                     .bind(end)
+                    .bind(series)
                     .fetch_all(&self.pool)
                     .await
                 {
@@ -320,11 +409,12 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn find_book(&self, number: u32) -> Option<Book> {
+    async fn find_book(&self, series: Series, number: u32) -> Option<Book> {
         let mut result = None;
         let start = Instant::now();
         match sqlx::query_as::<_, Book>(
-            "select * from hefte where number = $1")
+            "select * from hefte where series = $1 and number = $2")
+            .bind(series)
             .bind(number as i32)
             .fetch_one(&self.pool)
             .await
@@ -343,10 +433,12 @@ impl Db for DbPostgres {
         result
     }
 
-    async fn find_cover(&self, book_number: u32) -> Option<Cover> {
+    async fn find_cover(&self, series: Series, book_number: u32) -> Option<Cover> {
         let mut result = None;
         match sqlx::query_as::<_, Cover>(
-            "select * from covers where number = $1")
+            "select series, number, url, size, hash, source, width, height, phash from covers \
+                where series = $1 and number = $2")
+            .bind(series)
             .bind(book_number as i32)
             .fetch_one(&self.pool)
             .await
@@ -362,10 +454,26 @@ impl Db for DbPostgres {
         result
     }
 
-    async fn update_url_for_cover(&self, book_number: u32, url: String) -> DbResult<()>
+    async fn find_cover_hash(&self, series: Series, book_number: u32) -> Option<String> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            "select hash from covers where series = $1 and number = $2")
+            .bind(series)
+            .bind(book_number as i32)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(hash) => { hash.flatten() }
+            Err(e) => {
+                error!("Error trying to fetch the cover hash for {book_number}: {e}");
+                None
+            }
+        }
+    }
+
+    async fn update_url_for_cover(&self, series: Series, book_number: u32, url: String) -> DbResult<()>
     {
-        match sqlx::query!("update covers set url = $2::text where number = $1",
-                book_number as i32, url)
+        match sqlx::query!("update covers set url = $2::text where number = $1 and series = $3",
+                book_number as i32, url, series.code())
             .execute(&self.pool)
             .await
         {
@@ -380,8 +488,9 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn delete_cover(&self, book_number: u32) -> DbResult<()> {
-        match sqlx::query!("delete from covers where number = $1", book_number as i32)
+    async fn delete_cover(&self, series: Series, book_number: u32) -> DbResult<()> {
+        match sqlx::query!("delete from covers where number = $1 and series = $2", book_number as i32,
+                series.code())
             .execute(&self.pool)
             .await
         {
@@ -396,28 +505,322 @@ impl Db for DbPostgres {
         }
     }
 
-    async fn insert_cover(&self, book_number: u32, url: String, bytes: Vec<u8>) -> DbResult<()> {
-        match sqlx::query!("insert into covers (number, url, image, size) values ($1, $2, $3, $4)",
-            book_number as i32, url, bytes, bytes.len() as i32)
+    async fn insert_cover(&self, cover: Cover) -> DbResult<()> {
+        let book_number = cover.number;
+        match sqlx::query("insert into covers (number, url, size, series, hash, source, width, height, phash) \
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+                on conflict (series, number) do update set url = excluded.url, image = null, \
+                    size = excluded.size, hash = excluded.hash, source = excluded.source, \
+                    width = excluded.width, height = excluded.height, phash = excluded.phash \
+                where covers.source <> 'manual' or excluded.source = 'manual'")
+            .bind(cover.number)
+            .bind(&cover.url)
+            .bind(cover.size)
+            .bind(cover.series)
+            .bind(&cover.hash)
+            .bind(cover.source)
+            .bind(cover.width)
+            .bind(cover.height)
+            .bind(cover.phash)
             .execute(&self.pool)
             .await
         {
+            Ok(result) if result.rows_affected() == 0 => {
+                info!("Kept the manual cover of book {book_number}");
+                Err(InsertingCoverImage("that cover was uploaded manually".into(), book_number))
+            }
             Ok(_) => {
-                info!("Inserted new cover image for book {book_number}, url:${url}");
+                info!("Inserted new cover for book {book_number}, url:{:?}", cover.url);
                 Ok(())
             }
             Err(error) => {
                 error!("Error inserting new cover {}: {error}", book_number);
-                Err(InsertingCoverImage(error.to_string(), book_number as i32))
+                Err(InsertingCoverImage(error.to_string(), book_number))
+            }
+        }
+    }
+
+    async fn insert_cover_variants(&self, variants: Vec<CoverVariant>) -> DbResult<()> {
+        let Some(number) = variants.first().map(|v| v.number) else { return Ok(()) };
+        let mut tx = self.pool.begin().await
+            .map_err(|e| InsertingCoverVariants(e.to_string(), number))?;
+        for v in &variants {
+            sqlx::query("insert into cover_variants (series, number, size, format, width, height) \
+                    values ($1, $2, $3, $4, $5, $6) \
+                    on conflict (series, number, size, format) do update \
+                    set width = excluded.width, height = excluded.height, image = null")
+                .bind(v.series)
+                .bind(v.number)
+                .bind(v.size)
+                .bind(v.format)
+                .bind(v.width)
+                .bind(v.height)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| InsertingCoverVariants(e.to_string(), number))?;
+        }
+        tx.commit().await.map_err(|e| InsertingCoverVariants(e.to_string(), number))?;
+        info!("Inserted {} resized covers for book {number}", variants.len());
+        Ok(())
+    }
+
+    async fn find_cover_image(&self, key: CoverKey) -> Option<Vec<u8>> {
+        let query = match key.variant {
+            None => {
+                sqlx::query_scalar::<_, Option<Vec<u8>>>(
+                    "select image from covers where series = $1 and number = $2")
+                    .bind(key.series)
+                    .bind(key.number)
+            }
+            Some((size, format)) => {
+                sqlx::query_scalar::<_, Option<Vec<u8>>>(
+                    "select image from cover_variants where series = $1 and number = $2 \
+                        and size = $3 and format = $4")
+                    .bind(key.series)
+                    .bind(key.number)
+                    .bind(size)
+                    .bind(format)
+            }
+        };
+        match query.fetch_optional(&self.pool).await {
+            Ok(image) => { image.flatten() }
+            Err(e) => {
+                error!("Error trying to fetch the cover image {}: {e}", key.path());
+                None
+            }
+        }
+    }
+
+    async fn update_cover_image(&self, key: CoverKey, image: Option<Vec<u8>>) -> DbResult<()> {
+        let query = match key.variant {
+            None => {
+                sqlx::query("update covers set image = $3 where series = $1 and number = $2")
+                    .bind(key.series)
+                    .bind(key.number)
+                    .bind(image)
+            }
+            Some((size, format)) => {
+                sqlx::query("update cover_variants set image = $3 where series = $1 and number = $2 \
+                        and size = $4 and format = $5")
+                    .bind(key.series)
+                    .bind(key.number)
+                    .bind(image)
+                    .bind(size)
+                    .bind(format)
+            }
+        };
+        match query.execute(&self.pool).await {
+            Ok(_) => { Ok(()) }
+            Err(e) => {
+                error!("Error storing the cover image {}: {e}", key.path());
+                Err(InsertingCoverImage(e.to_string(), key.number))
+            }
+        }
+    }
+
+    async fn find_missing_covers(&self, limit: i64) -> Vec<MissingCover> {
+        match sqlx::query_as::<_, MissingCover>(
+            "select h.series, h.number, c.number is not null as has_image from hefte h \
+                left join covers c on c.series = h.series and c.number = h.number \
+                left join cover_fetch_failures f on f.series = h.series and f.number = h.number \
+                where (c.number is null or (c.url is null and c.source = 'perrypedia')) \
+                    and (f.number is null or (not f.permanent and f.next_attempt <= now())) \
+                order by h.series, h.number desc \
+                limit $1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(result) => { result }
+            Err(e) => {
+                error!("Error trying to find the missing covers: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn record_cover_fetch_failure(&self, series: Series, book_number: u32, error: String,
+        permanent: bool) -> DbResult<()>
+    {
+        match sqlx::query(
+            "insert into cover_fetch_failures (series, number, last_error, next_attempt, permanent) \
+                values ($1, $2, $3, now() + interval '1 hour', $4) \
+                on conflict (series, number) do update set \
+                    attempts = cover_fetch_failures.attempts + 1, \
+                    last_error = excluded.last_error, \
+                    last_attempt = now(), \
+                    permanent = excluded.permanent, \
+                    next_attempt = now() + least(interval '1 hour' * power(2, cover_fetch_failures.attempts), \
+                        interval '30 days')")
+            .bind(series)
+            .bind(book_number as i32)
+            .bind(error)
+            .bind(permanent)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => { Ok(()) }
+            Err(e) => {
+                error!("Error recording the failed download of cover {book_number}: {e}");
+                Err(RecordingCoverFailure(e.to_string(), book_number as i32))
+            }
+        }
+    }
+
+    async fn delete_cover_fetch_failure(&self, series: Series, book_number: u32) -> DbResult<()> {
+        match sqlx::query("delete from cover_fetch_failures where series = $1 and number = $2")
+            .bind(series)
+            .bind(book_number as i32)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => { Ok(()) }
+            Err(e) => {
+                error!("Error deleting the failed download of cover {book_number}: {e}");
+                Err(RecordingCoverFailure(e.to_string(), book_number as i32))
+            }
+        }
+    }
+
+    async fn find_cover_fetch_failure(&self, series: Series, book_number: u32) -> Option<CoverFetchFailure> {
+        match sqlx::query_as::<_, CoverFetchFailure>(
+            "select * from cover_fetch_failures where series = $1 and number = $2")
+            .bind(series)
+            .bind(book_number as i32)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(result) => { result }
+            Err(e) => {
+                error!("Error fetching the failed download of cover {book_number}: {e}");
+                None
+            }
+        }
+    }
+
+    async fn find_cover_fetch_failures(&self) -> Vec<CoverFetchFailure> {
+        match sqlx::query_as::<_, CoverFetchFailure>(
+            "select * from cover_fetch_failures order by last_attempt desc limit 200")
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(result) => { result }
+            Err(e) => {
+                error!("Error fetching the failed cover downloads: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn fetch_cover_stats(&self) -> Vec<CoverStats> {
+        match sqlx::query_as::<_, CoverStats>(
+            "select h.series, count(*) as books, count(c.number) as covers, \
+                    count(c.number) filter (where c.url is null and c.source = 'perrypedia') as missing_urls, \
+                    count(f.number) as failures \
+                from hefte h \
+                left join covers c on c.series = h.series and c.number = h.number \
+                left join cover_fetch_failures f on f.series = h.series and f.number = h.number \
+                group by h.series order by h.series")
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(result) => { result }
+            Err(e) => {
+                error!("Error computing the cover statistics: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_covers(&self) -> Vec<Cover> {
+        match sqlx::query_as::<_, Cover>(
+            "select series, number, url, size, hash, source, width, height, phash from covers \
+                order by series, number")
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(result) => { result }
+            Err(e) => {
+                error!("Error fetching the covers: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn update_cover_analysis(&self, series: Series, book_number: u32, width: i32, height: i32,
+        phash: Option<i64>) -> DbResult<()>
+    {
+        match sqlx::query("update covers set width = $3, height = $4, phash = $5 \
+                where series = $1 and number = $2")
+            .bind(series)
+            .bind(book_number as i32)
+            .bind(width)
+            .bind(height)
+            .bind(phash)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => { Ok(()) }
+            Err(e) => {
+                error!("Error storing the analysis of cover {book_number}: {e}");
+                Err(UpdatingCoverAnalysis(e.to_string(), book_number as i32))
+            }
+        }
+    }
+
+    async fn find_placeholder_hashes(&self) -> Vec<PlaceholderHash> {
+        match sqlx::query_as::<_, PlaceholderHash>(
+            "select * from cover_placeholder_hashes order by created")
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(result) => { result }
+            Err(e) => {
+                error!("Error fetching the placeholder hashes: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn insert_placeholder_hash(&self, phash: i64, series: Series, book_number: u32) -> DbResult<()> {
+        match sqlx::query("insert into cover_placeholder_hashes (phash, series, number) values ($1, $2, $3) \
+                on conflict (phash) do nothing")
+            .bind(phash)
+            .bind(series)
+            .bind(book_number as i32)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => {
+                info!("Marked the cover of {series} {book_number} as a placeholder");
+                Ok(())
+            }
+            Err(e) => {
+                error!("Error inserting the placeholder hash {phash}: {e}");
+                Err(UpdatingPlaceholderHash(e.to_string(), phash))
+            }
+        }
+    }
+
+    async fn delete_placeholder_hash(&self, phash: i64) -> DbResult<()> {
+        match sqlx::query("delete from cover_placeholder_hashes where phash = $1")
+            .bind(phash)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => { Ok(()) }
+            Err(e) => {
+                error!("Error deleting the placeholder hash {phash}: {e}");
+                Err(UpdatingPlaceholderHash(e.to_string(), phash))
             }
         }
     }
 
     async fn insert_summary(&self, summary: Summary) -> DbResult<()> {
         match sqlx::query!("insert into summaries (number, english_title, author_name, author_email, \
-            date, summary, time) values ($1, $2, $3, $4, $5, $6, $7)",
+            date, summary, time, series) values ($1, $2, $3, $4, $5, $6, $7, $8)",
                 summary.number, summary.english_title, summary.author_name, summary.author_email,
-                summary.date, summary.summary, summary.time)
+                summary.date, summary.summary, summary.time, summary.series.code())
             .execute(&self.pool)
             .await
         {
@@ -435,9 +838,9 @@ impl Db for DbPostgres {
     async fn update_summary(&self, summary: Summary) -> DbResult<()> {
         match sqlx::query!("update summaries set english_title = $2::text, author_name = $3::text,\
          author_email = $4::text, date = $5::text, summary = $6::text, time = $7::text \
-         where number = $1",
+         where number = $1 and series = $8",
                 summary.number, summary.english_title, summary.author_name, summary.author_email,
-                summary.date, summary.summary, summary.time)
+                summary.date, summary.summary, summary.time, summary.series.code())
             .execute(&self.pool)
             .await
         {
@@ -453,12 +856,13 @@ impl Db for DbPostgres {
     }
 
     async fn update_or_insert_book(&self, book: Book) -> DbResult<()> {
-        match self.find_book(book.number as u32).await {
+        match self.find_book(book.series, book.number as u32).await {
             Some(_) => {
                 match sqlx::query!("update hefte set title = $2::text, author = $3::text,\
-                     german_file = $4::text \
-                     where number = $1",
-                book.number, book.title, book.author, book.german_file)
+                     german_file = $4::text, published = $6 \
+                     where number = $1 and series = $5",
+                book.number, book.title, book.author, book.german_file, book.series.code(),
+                book.published)
                     .execute(&self.pool)
                     .await
                 {
@@ -472,9 +876,10 @@ impl Db for DbPostgres {
                 }
             }
             None => {
-                match sqlx::query!("insert into hefte (number, title, author, german_file)\
-                        values ($1, $2::text, $3::text, $4::text)",
-                        book.number, book.title, book.author, book.german_file)
+                match sqlx::query!("insert into hefte (number, title, author, german_file, series, published)\
+                        values ($1, $2::text, $3::text, $4::text, $5, $6)",
+                        book.number, book.title, book.author, book.german_file, book.series.code(),
+                        book.published)
                     .execute(&self.pool)
                     .await
                 {
@@ -517,13 +922,13 @@ impl Db for DbPostgres {
     }
 
     async fn insert_summary_in_pending(&self, book: Book, summary: Summary) -> DbResult<()> {
-        // Note: not inserting `published`
         match sqlx::query!("insert into pending (number, german_title, author,\
-            english_title, author_name, author_email, date_summary, summary) \
-            values($1, $2::text, $3::text, $4::text, $5::text, $6::text, $7::text, $8::text)",
+            english_title, author_name, author_email, date_summary, summary, series, published) \
+            values($1, $2::text, $3::text, $4::text, $5::text, $6::text, $7::text, $8::text, $9, $10)",
                 summary.number, book.title, book.author,
                 summary.english_title, summary.author_name, summary.author_email,
-                summary.date, summary.summary)
+                summary.date, summary.summary, summary.series.code(),
+                book.published.map(|d| d.to_string()))
             .execute(&self.pool)
             .await
         {
@@ -558,14 +963,15 @@ impl Db for DbPostgres {
         let english_title = cycle.english_title.clone();
         let cycle_number = cycle.number;
         match sqlx::query(
-            "insert into cycles (number, german_title, english_title, short_title, start, \"end\") \
-             values ($1, $2, $3, $4, $5, $6)")
+            "insert into cycles (number, german_title, english_title, short_title, start, \"end\", series) \
+             values ($1, $2, $3, $4, $5, $6, $7)")
             .bind(cycle.number)
             .bind(cycle.german_title)
             .bind(cycle.english_title) 
             .bind(cycle.short_title)
             .bind(cycle.start)
             .bind(cycle.end)
+            .bind(cycle.series)
             .execute(&self.pool)
             .await
         {
@@ -580,4 +986,606 @@ impl Db for DbPostgres {
         }
     }
 
+    async fn update_cycle(&self, number: i32, cycle: Cycle) -> DbResult<()> {
+        match sqlx::query(
+            "update cycles set number = $1, german_title = $2, english_title = $3, short_title = $4, \
+                start = $5, \"end\" = $6 \
+             where number = $7 and series = $8")
+            .bind(cycle.number)
+            .bind(&cycle.german_title)
+            .bind(&cycle.english_title)
+            .bind(&cycle.short_title)
+            .bind(cycle.start)
+            .bind(cycle.end)
+            .bind(number)
+            .bind(cycle.series)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                Err(Error::UpdatingCycle("No such cycle".into(), number))
+            }
+            Ok(_) => {
+                info!("Updated cycle {number}: {cycle:?}");
+                Ok(())
+            }
+            Err(e) => {
+                Err(Error::UpdatingCycle(e.to_string(), number))
+            }
+        }
+    }
+
+    async fn delete_cycle(&self, series: Series, number: i32) -> DbResult<()> {
+        match sqlx::query("delete from cycles where series = $1 and number = $2")
+            .bind(series)
+            .bind(number)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => {
+                info!("Deleted cycle {number}");
+                Ok(())
+            }
+            Err(e) => {
+                Err(Error::DeletingCycle(e.to_string(), number))
+            }
+        }
+    }
+
+    async fn update_book_with_edits(&self, book: Book, edits: Vec<BookEdit>) -> DbResult<()> {
+        let number = book.number;
+        let result: Result<(), sqlx::Error> = async {
+            let mut tx = self.pool.begin().await?;
+            let updated = sqlx::query(
+                "update hefte set title = $3, author = $4, german_file = $5, published = $6 \
+                 where series = $1 and number = $2")
+                .bind(book.series)
+                .bind(book.number)
+                .bind(&book.title)
+                .bind(&book.author)
+                .bind(&book.german_file)
+                .bind(book.published)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() == 0 {
+                sqlx::query(
+                    "insert into hefte (series, number, title, author, german_file, published) \
+                     values ($1, $2, $3, $4, $5, $6)")
+                    .bind(book.series)
+                    .bind(book.number)
+                    .bind(&book.title)
+                    .bind(&book.author)
+                    .bind(&book.german_file)
+                    .bind(book.published)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            for edit in edits {
+                sqlx::query(
+                    "insert into book_edits (series, number, login, field, old_value, new_value, date) \
+                     values ($1, $2, $3, $4, $5, $6, $7)")
+                    .bind(edit.series)
+                    .bind(edit.number)
+                    .bind(edit.login)
+                    .bind(edit.field)
+                    .bind(edit.old_value)
+                    .bind(edit.new_value)
+                    .bind(edit.date)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await
+        }.await;
+
+        match result {
+            Ok(_) => {
+                info!("Saved book {number}: \"{}\"", book.title);
+                Ok(())
+            }
+            Err(error) => {
+                error!("Error saving book {number} and its edits: {error}");
+                Err(UpdatingBook(error.to_string(), number))
+            }
+        }
+    }
+
+    async fn find_book_edits(&self, series: Series, book_number: Option<u32>) -> Vec<BookEdit> {
+        match sqlx::query_as::<_, BookEdit>(
+            "select * from book_edits where series = $1 and ($2::integer is null or number = $2) \
+             order by id desc limit 200")
+            .bind(series)
+            .bind(book_number.map(|n| n as i32))
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(edits) => { edits }
+            Err(e) => {
+                error!("find_book_edits(): couldn't retrieve the book edits: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_books_published_between(&self, series: Series, start: NaiveDate, end: NaiveDate)
+        -> Vec<Book>
+    {
+        match sqlx::query_as::<_, Book>(
+            "select * from hefte where series = $1 and published between $2 and $3 order by published, number")
+            .bind(series)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(books) => { books }
+            Err(e) => {
+                error!("find_books_published_between(): couldn't retrieve books published \
+                    between {start} and {end}: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn fetch_cycle_stats(&self, series: Series) -> Vec<CycleStats> {
+        // Group by cycle and author first so the top contributors come out of the same query
+        match sqlx::query_as::<_, CycleStats>(
+            "with per_author as (\
+                select c.number, s.author_name, count(h.number) as books, count(s.number) as summaries, \
+                    max(nullif(s.date, '')) as last_date \
+                from cycles c \
+                left join hefte h on h.series = c.series and h.number between c.start and c.\"end\" \
+                left join summaries s on s.series = h.series and s.number = h.number \
+                where c.series = $1 \
+                group by c.number, s.author_name) \
+             select number, sum(books)::bigint as books, sum(summaries)::bigint as summaries, \
+                max(last_date) as last_date, \
+                coalesce((array_agg(author_name order by summaries desc, author_name) \
+                    filter (where author_name != ''))[1:3], '{}') as top_contributors \
+             from per_author \
+             group by number \
+             order by number")
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(stats) => { stats }
+            Err(e) => {
+                error!("fetch_cycle_stats(): couldn't retrieve the cycle statistics: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_books_outside_cycles(&self, series: Series) -> Vec<Book> {
+        match sqlx::query_as::<_, Book>(
+            "select * from hefte h \
+             where h.series = $1 and not exists (select 1 from cycles c \
+                where c.series = h.series and h.number between c.start and c.\"end\") \
+             order by h.number")
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(books) => { books }
+            Err(e) => {
+                error!("find_books_outside_cycles(): {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_comments(&self, series: Series, book_number: u32, status: CommentStatus) -> Vec<Comment> {
+        match sqlx::query_as::<_, Comment>(
+            "select * from comments where series = $3 and number = $1 and status = $2 order by id")
+            .bind(book_number as i32)
+            .bind(status)
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(comments) => { comments }
+            Err(e) => {
+                error!("find_comments(): couldn't retrieve comments for {series} {book_number}: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_comments_by_status(&self, status: CommentStatus) -> Vec<Comment> {
+        match sqlx::query_as::<_, Comment>(
+            "select * from comments where status = $1 order by id desc")
+            .bind(status)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(comments) => { comments }
+            Err(e) => {
+                error!("find_comments_by_status(): couldn't retrieve comments: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_comment(&self, id: i32) -> Option<Comment> {
+        match sqlx::query_as::<_, Comment>("select * from comments where id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(comment) => { comment }
+            Err(e) => {
+                error!("find_comment(): couldn't retrieve comment {id}: {e}");
+                None
+            }
+        }
+    }
+
+    async fn find_comment_by_token(&self, token: &str) -> Option<Comment> {
+        match sqlx::query_as::<_, Comment>("select * from comments where verification_token = $1")
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(comment) => { comment }
+            Err(e) => {
+                error!("find_comment_by_token(): couldn't retrieve comment: {e}");
+                None
+            }
+        }
+    }
+
+    async fn insert_comment(&self, comment: Comment) -> DbResult<()> {
+        let (series, number) = (comment.series, comment.number);
+        match sqlx::query(
+            "insert into comments (number, parent_id, login, author_name, author_email, text, \
+             status, verification_token, date, series) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)")
+            .bind(comment.number)
+            .bind(comment.parent_id)
+            .bind(comment.login)
+            .bind(comment.author_name)
+            .bind(comment.author_email)
+            .bind(comment.text)
+            .bind(comment.status)
+            .bind(comment.verification_token)
+            .bind(comment.date)
+            .bind(comment.series)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => {
+                info!("Inserted new comment on summary {series} {number}");
+                Ok(())
+            }
+            Err(error) => {
+                error!("Error inserting comment on summary {series} {number}: {error}");
+                Err(InsertingComment(error.to_string(), number))
+            }
+        }
+    }
+
+    async fn update_comment_status(&self, id: i32, status: CommentStatus) -> DbResult<()> {
+        match sqlx::query("update comments set status = $2, verification_token = null where id = $1")
+            .bind(id)
+            .bind(status)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => {
+                info!("Updated comment {id} to {status:?}");
+                Ok(())
+            }
+            Err(error) => {
+                Err(UpdatingComment(error.to_string(), id))
+            }
+        }
+    }
+
+    async fn delete_comment(&self, id: i32) -> DbResult<()> {
+        match sqlx::query("delete from comments where id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => {
+                info!("Deleted comment {id}");
+                Ok(())
+            }
+            Err(error) => {
+                error!("Error deleting comment {id}: {error}");
+                Err(DeletingComment(error.to_string(), id))
+            }
+        }
+    }
+
+    async fn find_reading(&self, login: &str, series: Series, book_number: u32) -> Option<Reading> {
+        match sqlx::query_as::<_, Reading>(
+            "select * from readings where login = $1 and series = $3 and number = $2")
+            .bind(login)
+            .bind(book_number as i32)
+            .bind(series)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(reading) => { reading }
+            Err(e) => {
+                error!("find_reading(): couldn't retrieve reading of {series} {book_number} for {login}: {e}");
+                None
+            }
+        }
+    }
+
+    async fn update_reading(&self, reading: Reading) -> DbResult<()> {
+        let (series, number) = (reading.series, reading.number);
+        match sqlx::query(
+            "insert into readings (login, number, status, rating, series) values ($1, $2, $3, $4, $5) \
+             on conflict (login, series, number) do update set status = $3, rating = $4")
+            .bind(reading.login.clone())
+            .bind(reading.number)
+            .bind(reading.status)
+            .bind(reading.rating)
+            .bind(series)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => {
+                info!("Updated reading of {series} {number} for {}: {:?} {:?}", reading.login,
+                    reading.status, reading.rating);
+                Ok(())
+            }
+            Err(error) => {
+                Err(UpdatingReading(error.to_string(), number))
+            }
+        }
+    }
+
+    async fn find_ratings(&self, series: Series, start: i32, end: i32) -> Vec<Rating> {
+        match sqlx::query_as::<_, Rating>(
+            "select number, avg(rating)::real as average, count(rating) as count from readings \
+             where series = $3 and number between $1 and $2 and rating is not null group by number")
+            .bind(start)
+            .bind(end)
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(ratings) => { ratings }
+            Err(e) => {
+                error!("find_ratings(): couldn't retrieve ratings for {series} {start}-{end}: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn fetch_reading_progress(&self, login: &str, series: Series) -> Vec<CycleProgress> {
+        match sqlx::query_as::<_, CycleProgress>(
+            "select c.number, c.english_title, c.german_title, count(h.number) as books, \
+                count(r.number) filter (where r.status = 'read') as read \
+             from cycles c \
+             left join hefte h on h.series = c.series and h.number between c.start and c.\"end\" \
+             left join readings r on r.series = h.series and r.number = h.number and r.login = $1 \
+             where c.series = $2 \
+             group by c.number, c.english_title, c.german_title \
+             order by c.number")
+            .bind(login)
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(progress) => { progress }
+            Err(e) => {
+                error!("fetch_reading_progress(): couldn't retrieve progress for {login}: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn fetch_entities(&self) -> Vec<Entity> {
+        match sqlx::query_as::<_, Entity>(&entities_query(""))
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(entities) => { entities }
+            Err(e) => {
+                error!("fetch_entities(): couldn't retrieve entities: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_entity(&self, slug: &str) -> Option<Entity> {
+        match sqlx::query_as::<_, Entity>(&entities_query("where e.slug = $1"))
+            .bind(slug)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(entity) => { entity }
+            Err(e) => {
+                error!("find_entity(): couldn't retrieve entity {slug}: {e}");
+                None
+            }
+        }
+    }
+
+    async fn insert_entity(&self, entity: Entity) -> DbResult<()> {
+        let name = entity.name.clone();
+        let result: Result<(), sqlx::Error> = async {
+            let mut tx = self.pool.begin().await?;
+            let id: i32 = sqlx::query_scalar(
+                "insert into entities (slug, name, kind) values ($1, $2, $3) returning id")
+                .bind(entity.slug)
+                .bind(entity.name)
+                .bind(entity.kind)
+                .fetch_one(&mut *tx)
+                .await?;
+            for alias in entity.aliases {
+                sqlx::query("insert into entity_aliases (entity_id, alias) values ($1, $2) \
+                        on conflict do nothing")
+                    .bind(id)
+                    .bind(alias)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await
+        }.await;
+
+        match result {
+            Ok(_) => {
+                info!("Inserted new entity \"{name}\"");
+                Ok(())
+            }
+            Err(error) => {
+                error!("Error inserting entity {name}: {error}");
+                Err(InsertingEntity(error.to_string(), name))
+            }
+        }
+    }
+
+    async fn find_summary_entities(&self, series: Series, book_number: u32) -> Vec<Entity> {
+        match sqlx::query_as::<_, Entity>(&entities_query(
+                "join summary_entities s on s.entity_id = e.id where s.series = $2 and s.number = $1"))
+            .bind(book_number as i32)
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(entities) => { entities }
+            Err(e) => {
+                error!("find_summary_entities(): couldn't retrieve entities of {series} {book_number}: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn update_summary_entities(&self, series: Series, book_number: u32, entity_ids: Vec<i32>) -> DbResult<()> {
+        let number = book_number as i32;
+        let result: Result<(), sqlx::Error> = async {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query("delete from summary_entities where series = $2 and number = $1")
+                .bind(number)
+                .bind(series)
+                .execute(&mut *tx)
+                .await?;
+            for id in &entity_ids {
+                sqlx::query("insert into summary_entities (number, entity_id, series) values ($1, $2, $3)")
+                    .bind(number)
+                    .bind(id)
+                    .bind(series)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await
+        }.await;
+
+        match result {
+            Ok(_) => {
+                info!("Tagged {} entities on summary {series} {book_number}", entity_ids.len());
+                Ok(())
+            }
+            Err(error) => {
+                Err(UpdatingSummaryEntities(error.to_string(), number))
+            }
+        }
+    }
+
+    async fn find_appearances(&self, entity_id: i32) -> Vec<Appearance> {
+        match sqlx::query_as::<_, Appearance>(
+            "select s.series, s.number, s.english_title, h.title as german_title \
+             from summary_entities se \
+             join summaries s on s.series = se.series and s.number = se.number \
+             left join hefte h on h.series = se.series and h.number = se.number \
+             where se.entity_id = $1 \
+             order by h.published nulls last, s.series, s.number")
+            .bind(entity_id)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(appearances) => { appearances }
+            Err(e) => {
+                error!("find_appearances(): couldn't retrieve appearances of {entity_id}: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn fetch_all_summaries(&self) -> Vec<Summary> {
+        match sqlx::query_as::<_, Summary>("select * from summaries order by series, number")
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(summaries) => { summaries }
+            Err(e) => {
+                error!("fetch_all_summaries(): couldn't retrieve summaries: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn find_summary_numbers(&self, series: Series, numbers: Vec<u32>) -> Vec<u32> {
+        let numbers: Vec<i32> = numbers.iter().map(|n| *n as i32).collect();
+        match sqlx::query_scalar::<_, i32>("select number from summaries where series = $2 and number = any($1)")
+            .bind(numbers)
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(found) => { found.iter().map(|n| *n as u32).collect() }
+            Err(e) => {
+                error!("find_summary_numbers(): {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn update_references(&self, series: Series, from: u32, to: Vec<u32>) -> DbResult<()> {
+        let from = from as i32;
+        let result: Result<(), sqlx::Error> = async {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query("delete from summary_references where series = $2 and from_number = $1")
+                .bind(from)
+                .bind(series)
+                .execute(&mut *tx)
+                .await?;
+            for n in &to {
+                sqlx::query("insert into summary_references (from_number, to_number, series) values ($1, $2, $3) \
+                        on conflict do nothing")
+                    .bind(from)
+                    .bind(*n as i32)
+                    .bind(series)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await
+        }.await;
+
+        match result {
+            Ok(_) => {
+                debug!("Summary {series} {from} references {to:?}");
+                Ok(())
+            }
+            Err(error) => {
+                Err(UpdatingReferences(error.to_string(), from))
+            }
+        }
+    }
+
+    async fn find_backlinks(&self, series: Series, book_number: u32) -> Vec<Appearance> {
+        match sqlx::query_as::<_, Appearance>(
+            "select s.series, s.number, s.english_title, h.title as german_title \
+             from summary_references r \
+             join summaries s on s.series = r.series and s.number = r.from_number \
+             left join hefte h on h.series = r.series and h.number = r.from_number \
+             where r.series = $2 and r.to_number = $1 and r.from_number != $1 \
+             order by s.number")
+            .bind(book_number as i32)
+            .bind(series)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(backlinks) => { backlinks }
+            Err(e) => {
+                error!("find_backlinks(): couldn't retrieve backlinks of {series} {book_number}: {e}");
+                Vec::new()
+            }
+        }
+    }
 }
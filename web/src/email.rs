@@ -5,10 +5,11 @@ use lettre::transport::smtp::authentication::Credentials;
 use tracing::{error, info, warn};
 use crate::config::Config;
 use crate::constants::ADMIN;
-use crate::entities::Summary;
+use crate::entities::{CoverSize, Series, Summary};
 use crate::errors::Error::{EmailError, Unknown};
 use crate::errors::{Error, PrResult, PrResultBuilder};
 use crate::logic::send_summary_to_group;
+use crate::references::render_summary_text;
 use crate::PerryState;
 use crate::url::Urls;
 
@@ -68,10 +69,11 @@ impl Email {
         -> Result<String, Error>
     {
         let book_number = summary.number as u32;
+        let series = summary.series;
         let (book, cycle_number, cover_url) = tokio::join!(
-            state.db.find_book(book_number),
-            state.db.find_cycle_by_book(book_number),
-            state.cover_finder.find_cover_url(book_number),
+            state.db.find_book(series, book_number),
+            state.db.find_cycle_by_book(series, book_number),
+            state.cover_finder.find_cover_url(series, book_number, CoverSize::Medium),
         );
 
         let cycle_name = match cycle_number {
@@ -85,7 +87,8 @@ impl Email {
 
         let english_title = summary.english_title.clone();
         let summary_author_name = summary.author_name.clone();
-        let summary_text = summary.summary.clone();
+        let summary_text = render_summary_text(&state.db, summary.series, &summary.summary,
+            &format!("https://{host}")).await;
         match book {
             Some(book) => {
                 let template = SendEmailTemplate {
@@ -96,7 +99,7 @@ impl Email {
                     heft_author: book.author,
                     summary_author_name,
                     summary_text,
-                    summary_url: format!("{}{}", host, Urls::summary(book_number as i32)),
+                    summary_url: format!("{}{}", host, Urls::summary(series, book_number as i32)),
                 };
                 let content = template.render().unwrap();
                 Ok(content.into())
@@ -109,8 +112,8 @@ impl Email {
     }
 }
 
-pub async fn api_send_email_logic(state: &PerryState, book_number: u32) -> PrResult {
-    if let Some(summary) = state.db.find_summary(book_number).await {
+pub async fn api_send_email_logic(state: &PerryState, series: Series, book_number: u32) -> PrResult {
+    if let Some(summary) = state.db.find_summary(series, book_number).await {
         let _ = send_summary_to_group(&state, &summary).await;
     }
 
@@ -1,6 +1,8 @@
 use std::fmt::{Display, Formatter};
 use bon::Builder;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use crate::cover_path::cover_path;
 
 #[derive(Builder, Clone, Debug, sqlx::FromRow)]
 pub struct User {
@@ -12,18 +14,276 @@ pub struct User {
     pub salt: Option<Vec<u8>>,
 }
 
+/// The book series we summarize. Everything used to be keyed on the number alone, which is
+/// now the number within the main series (Erstauflage), the default. URL's use the lowercase
+/// code ("/neo/summaries/12").
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar")]
+pub enum Series {
+    #[default]
+    #[sqlx(rename = "PR")]
+    #[serde(rename = "PR", alias = "pr")]
+    Pr,
+    #[sqlx(rename = "NEO")]
+    #[serde(rename = "NEO", alias = "neo")]
+    Neo,
+    #[sqlx(rename = "ATLAN")]
+    #[serde(rename = "ATLAN", alias = "atlan")]
+    Atlan,
+}
+
+impl Series {
+    pub const ALL: [Series; 3] = [Series::Pr, Series::Neo, Series::Atlan];
+
+    /// What's stored in the `series` columns
+    pub fn code(&self) -> &'static str {
+        match self {
+            Series::Pr => "PR",
+            Series::Neo => "NEO",
+            Series::Atlan => "ATLAN",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Series::Pr => "Perry Rhodan",
+            Series::Neo => "Perry Rhodan NEO",
+            Series::Atlan => "Atlan",
+        }
+    }
+
+    /// "neo" in "/neo/summaries/12"
+    pub fn from_path(path: &str) -> Option<Series> {
+        Series::ALL.into_iter().find(|s| s.code().eq_ignore_ascii_case(path))
+    }
+
+    /// PerryPedia's prefix for the pages and covers of this series: "Quelle:PRN12", "PRN012.jpg"
+    pub fn perry_pedia_prefix(&self) -> &'static str {
+        match self {
+            Series::Pr => "PR",
+            Series::Neo => "PRN",
+            Series::Atlan => "A",
+        }
+    }
+
+    /// The file name of a cover on PerryPedia, which pads numbers differently for each series
+    pub fn perry_pedia_cover(&self, number: u32) -> String {
+        let prefix = self.perry_pedia_prefix();
+        match self {
+            Series::Pr => format!("{prefix}{number:04}.jpg"),
+            Series::Neo | Series::Atlan => format!("{prefix}{number:03}.jpg"),
+        }
+    }
+}
+
+impl Display for Series {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// The image itself is in the CoverStore
 #[derive(Clone, Debug, sqlx::FromRow)]
 pub struct Cover {
+    #[sqlx(default)]
+    pub series: Series,
     pub number: i32,
     pub url: Option<String>,
-    pub image: Vec<u8>,
+    /// Of the image, in bytes
     pub size: i32,
+    /// SHA-256 of the image
+    #[sqlx(default)]
+    pub hash: Option<String>,
+    #[sqlx(default)]
+    pub source: CoverSource,
+    /// Of the original, `None` until the cover is analyzed
+    #[sqlx(default)]
+    pub width: Option<i32>,
+    #[sqlx(default)]
+    pub height: Option<i32>,
+    /// Perceptual hash of the original, see cover_quality::perceptual_hash()
+    #[sqlx(default)]
+    pub phash: Option<i64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CoverSource {
+    #[default]
+    PerryPedia,
+    /// Uploaded by an admin, never replaced by a download
+    Manual,
+    /// Read from COVER_SOURCE_DIR
+    Directory,
+    /// Generated when no provider has the cover, never stored
+    Placeholder,
+}
+
+/// The sizes a cover is served in ("/covers/12?size=thumb"), each one a bounding box the
+/// original is shrunk into (never enlarged)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CoverSize {
+    /// The index and the cycle pages
+    Thumb,
+    /// The summary page and the emails
+    Medium,
+    #[default]
+    Full,
+}
+
+impl CoverSize {
+    pub const ALL: [CoverSize; 3] = [CoverSize::Thumb, CoverSize::Medium, CoverSize::Full];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoverSize::Thumb => "thumb",
+            CoverSize::Medium => "medium",
+            CoverSize::Full => "full",
+        }
+    }
+
+    /// (width, height)
+    pub fn bounds(&self) -> (u32, u32) {
+        match self {
+            CoverSize::Thumb => (120, 180),
+            CoverSize::Medium => (300, 450),
+            CoverSize::Full => (800, 1200),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CoverFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl CoverFormat {
+    pub const ALL: [CoverFormat; 2] = [CoverFormat::Jpeg, CoverFormat::Webp];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoverFormat::Jpeg => "jpeg",
+            CoverFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Identifies an image in a CoverStore: the original cover of a book, or one of its variants
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CoverKey {
+    pub series: Series,
+    pub number: i32,
+    pub variant: Option<(CoverSize, CoverFormat)>,
+}
+
+impl CoverKey {
+    pub fn original(series: Series, number: i32) -> Self {
+        CoverKey { series, number, variant: None }
+    }
+
+    pub fn variant(series: Series, number: i32, size: CoverSize, format: CoverFormat) -> Self {
+        CoverKey { series, number, variant: Some((size, format)) }
+    }
+
+    /// Every key a book can have
+    pub fn all(series: Series, number: i32) -> Vec<CoverKey> {
+        let mut result = vec![Self::original(series, number)];
+        for size in CoverSize::ALL {
+            for format in CoverFormat::ALL {
+                result.push(Self::variant(series, number, size, format));
+            }
+        }
+        result
+    }
+
+    /// Where the image goes in a file system or a bucket: "PR/12/original", "PR/12/thumb.webp"
+    pub fn path(&self) -> String {
+        cover_path(self.series.code(), self.number, self.variant.map(|(size, format)| (size.name(), format.name())))
+    }
+}
+
+/// One resized version of a cover
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct CoverVariant {
+    pub series: Series,
+    pub number: i32,
+    pub size: CoverSize,
+    pub format: CoverFormat,
+    pub width: i32,
+    pub height: i32,
+    /// Empty when read from the database, the image is in the CoverStore
+    #[sqlx(skip)]
+    pub image: Vec<u8>,
+}
+
+impl CoverVariant {
+    pub fn key(&self) -> CoverKey {
+        CoverKey::variant(self.series, self.number, self.size, self.format)
+    }
+}
+
+/// A book the cover prefetch should download: it has no cover yet, or one whose PerryPedia
+/// URL is unknown
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct MissingCover {
+    pub series: Series,
+    pub number: i32,
+    /// The image is there, only the URL is missing
+    pub has_image: bool,
+}
+
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct CoverFetchFailure {
+    pub series: Series,
+    pub number: i32,
+    pub attempts: i32,
+    pub last_error: String,
+    pub last_attempt: DateTime<Utc>,
+    pub next_attempt: DateTime<Utc>,
+    /// The image was found but can't be used, it won't be downloaded again
+    pub permanent: bool,
+}
+
+/// The perceptual hash of an image that isn't a cover, e.g. the PerryPedia placeholder
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct PlaceholderHash {
+    pub phash: i64,
+    /// The book it was marked on
+    pub series: Series,
+    pub number: i32,
+    pub created: DateTime<Utc>,
+}
+
+/// How many books of a series have their cover
+#[derive(Clone, Debug, Default, Serialize, sqlx::FromRow)]
+pub struct CoverStats {
+    pub series: Series,
+    pub books: i64,
+    pub covers: i64,
+    pub missing_urls: i64,
+    pub failures: i64,
 }
 
 impl User {
     pub fn can_post(&self) -> bool {
         self.login == "cbeust" || self.login == "jerry_s"
     }
+
+    pub fn is_admin(&self) -> bool {
+        self.level == 0
+    }
+
+    /// Editors can tag summaries and fix book metadata
+    pub fn is_editor(&self) -> bool {
+        self.can_post() || self.is_admin()
+    }
 }
 
 impl Display for User {
@@ -34,6 +294,10 @@ impl Display for User {
 
 #[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Summary {
+    #[builder(default)]
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
     pub number: i32,
     pub author_email: String,
     pub author_name: String,
@@ -45,6 +309,10 @@ pub struct Summary {
 
 #[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Cycle {
+    #[builder(default)]
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
     pub number: i32,
     pub german_title: String,
     pub english_title: String,
@@ -56,17 +324,174 @@ pub struct Cycle {
 
 #[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Book {
+    #[builder(default)]
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
     pub number: i32,
     pub title: String,
     pub author: String,
-    // pub published: NaiveDate,
+    #[serde(default)]
+    pub published: Option<NaiveDate>,
     pub german_file: Option<String>,
 }
 
+/// One field of a book changed by an editor
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct BookEdit {
+    #[builder(default)]
+    pub id: i32,
+    #[builder(default)]
+    #[serde(default)]
+    pub series: Series,
+    pub number: i32,
+    pub login: Option<String>,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub date: String,
+}
+
 #[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
 pub struct PendingSummary {
     pub id: i32,
+    #[builder(default)]
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
     pub number: i32,
     pub english_title: String,
     pub date_summary: String,
-}
\ No newline at end of file
+    pub published: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CommentStatus {
+    /// Posted anonymously, waiting for the author to click the link in the verification email
+    Unverified,
+    /// Waiting in the moderation queue
+    #[default]
+    Pending,
+    Approved,
+}
+
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Comment {
+    pub id: i32,
+    #[builder(default)]
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
+    pub number: i32,
+    pub parent_id: Option<i32>,
+    pub login: Option<String>,
+    pub author_name: String,
+    pub author_email: String,
+    pub text: String,
+    pub status: CommentStatus,
+    pub verification_token: Option<String>,
+    pub date: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingStatus {
+    #[default]
+    Unread,
+    Reading,
+    Read,
+}
+
+/// Where a user is with a given book
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Reading {
+    pub login: String,
+    #[builder(default)]
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
+    pub number: i32,
+    pub status: ReadingStatus,
+    /// 1 to 5
+    pub rating: Option<i32>,
+}
+
+/// Ratings of a book aggregated over all the users
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Rating {
+    pub number: i32,
+    pub average: f32,
+    pub count: i64,
+}
+
+/// How many books of a cycle a user has read
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct CycleProgress {
+    pub number: i32,
+    pub english_title: String,
+    pub german_title: String,
+    pub books: i64,
+    pub read: i64,
+}
+
+/// How many books of a cycle have a summary, and who wrote them
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct CycleStats {
+    pub number: i32,
+    pub books: i64,
+    pub summaries: i64,
+    pub last_date: Option<String>,
+    /// The three most prolific authors of the cycle, most prolific first
+    pub top_contributors: Vec<String>,
+}
+
+impl CycleStats {
+    pub fn percentage(&self) -> u8 {
+        if self.books == 0 { 0 } else { (self.summaries * 100 / self.books).min(100) as u8 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    #[default]
+    Character,
+    Place,
+    Organization,
+}
+
+impl Display for EntityKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EntityKind::Character => "character",
+            EntityKind::Place => "place",
+            EntityKind::Organization => "organization",
+        })
+    }
+}
+
+/// A recurring character, place or organization that can be tagged on summaries
+#[derive(Builder, Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Entity {
+    pub id: i32,
+    pub slug: String,
+    pub name: String,
+    pub kind: EntityKind,
+    /// Other names used in the summaries, e.g. "Rhodan" for Perry Rhodan
+    pub aliases: Vec<String>,
+}
+
+/// A summary an entity is tagged on
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Appearance {
+    #[serde(default)]
+    #[sqlx(default)]
+    pub series: Series,
+    pub number: i32,
+    pub english_title: String,
+    pub german_title: Option<String>,
+}
@@ -11,6 +11,13 @@ pub enum OkContent {
     Html(String),
     Json(String),
     Image(Vec<u8>),
+    /// An image that can be cached, with its ETag
+    CachedImage(Vec<u8>, String),
+    /// The client's copy, identified by that ETag, is still current
+    NotModified(String),
+    /// Generated because no cover was found, flagged as such and cached briefly
+    PlaceholderImage(Vec<u8>),
+    NotFound,
     Redirect(String),
 }
 
@@ -39,6 +46,22 @@ impl PrResultBuilder {
         Ok(OkContent::Image(image))
     }
 
+    pub fn cached_image(image: Vec<u8>, etag: String) -> PrResult {
+        Ok(OkContent::CachedImage(image, etag))
+    }
+
+    pub fn not_modified(etag: String) -> PrResult {
+        Ok(OkContent::NotModified(etag))
+    }
+
+    pub fn placeholder_image(image: Vec<u8>) -> PrResult {
+        Ok(OkContent::PlaceholderImage(image))
+    }
+
+    pub fn not_found() -> PrResult {
+        Ok(OkContent::NotFound)
+    }
+
     pub fn redirect(url: String) -> PrResult {
         Ok(Redirect(url))
     }
@@ -50,23 +73,53 @@ pub enum Error {
     UpdatingSummary(String, i32),
     FetchingCycle(String, u32),
     FetchingCycles(String),
+    UpdatingCycle(String, i32),
+    DeletingCycle(String, i32),
     FetchingBook(String, u32),
+    FetchingMetadata(String, u32),
     InsertingBook(String, i32),
     UpdatingBook(String, i32),
+    InsertingCoverVariants(String, i32),
+    ResizingCover(String, i32),
+    RecordingCoverFailure(String, i32),
+    CoverNotAnImage(String, i32),
+    /// The limit, in bytes
+    CoverTooLarge(usize, i32),
+    CoverDimensions(u32, u32, i32),
+    CorruptCoverImage(String, i32),
+    StoringCoverImage(String, i32),
     UpdatingUser(String, String),
     IncorrectPassword(String),
     UnknownUser(String),
     InsertingInPending(String, Summary),
     InsertingCoverImage(String, i32),
     EmailError(String),
-    PerryPediaCouldNotFind(i32),
+    CoverNotFound(i32),
     CouldNotFindCoverImage(String, i32),
     UnknownCoverImageError(i32),
     DeletingCover(String, i32),
     UpdatingCoverUrl(String, i32),
+    UpdatingCoverAnalysis(String, i32),
+    UpdatingPlaceholderHash(String, i64),
+    DrawingMontage(String, u32),
+    InsertingComment(String, i32),
+    UpdatingComment(String, i32),
+    DeletingComment(String, i32),
+    UpdatingReading(String, i32),
+    InsertingEntity(String, String),
+    UpdatingSummaryEntities(String, i32),
+    UpdatingReferences(String, i32),
     Unknown(String),
 }
 
+impl Error {
+    /// The same image would fail the same way, downloading it again is pointless
+    pub fn is_permanent(&self) -> bool {
+        use Error::*;
+        matches!(self, CoverNotAnImage(..) | CoverTooLarge(..) | CoverDimensions(..) | CorruptCoverImage(..))
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use Error::*;
@@ -76,9 +129,20 @@ impl Display for Error {
             UpdatingSummary(e, n) => { format!("Error updating summary {n}: {e}") }
             FetchingCycles(e) => { format!("Error fetching cycles: {e}") }
             FetchingCycle(e, n) => { format!("Error fetching cycle {n}: {e}") }
+            UpdatingCycle(e, n) => { format!("Error updating cycle {n}: {e}") }
+            DeletingCycle(e, n) => { format!("Error deleting cycle {n}: {e}") }
             FetchingBook(e, n) => { format!("Error fetching book {n}: {e}") }
+            FetchingMetadata(e, n) => { format!("Error fetching the PerryPedia metadata of book {n}: {e}") }
             InsertingBook(e, n) => { format!("Error inserting book {n}: {e}") }
             UpdatingBook(e, n) => { format!("Error updating book {n}: {e}") }
+            InsertingCoverVariants(e, n) => { format!("Error inserting the resized covers of book {n}: {e}") }
+            ResizingCover(e, n) => { format!("Couldn't resize the cover of book {n}: {e}") }
+            StoringCoverImage(e, n) => { format!("Couldn't store the image of cover {n}: {e}") }
+            CoverNotAnImage(e, n) => { format!("The cover of book {n} isn't a JPEG, PNG, WebP or GIF image: {e}") }
+            CoverTooLarge(limit, n) => { format!("The cover of book {n} is larger than {} MB", limit / 1024 / 1024) }
+            CoverDimensions(w, h, n) => { format!("The cover of book {n} can't be {w}x{h} pixels") }
+            CorruptCoverImage(e, n) => { format!("The cover of book {n} can't be decoded: {e}") }
+            RecordingCoverFailure(e, n) => { format!("Couldn't record the failed download of cover {n}: {e}") }
             UpdatingUser(e, username) => { format!("Error updating user {username}: {e}") }
             IncorrectPassword(username) => { format!("Incorrect password for {username}") }
             UnknownUser(username) => { format!("Unknown user {username}") }
@@ -86,11 +150,21 @@ impl Display for Error {
             InsertingInPending(e, summary) => { format!("Couldn't insert #{} into PENDING: {e}",
                 summary.number) }
             EmailError(e) => { format!("Couldn't send email: {e}") }
-            PerryPediaCouldNotFind(n) => { format!("PerryPedia: could not find {n}") }
+            CoverNotFound(n) => { format!("No cover provider could find {n}") }
             CouldNotFindCoverImage(e, n) => { format!("Couldn't load cover image for {n}: {e}") }
             UnknownCoverImageError(n) => { format!("Couldn't load cover image for {n}") }
             DeletingCover(e, n) => { format!("Couldn't delete cover {n}: {e}") }
             UpdatingCoverUrl(e, n) => { format!("Couldn't update cover URL for book {n}: {e}") }
+            UpdatingCoverAnalysis(e, n) => { format!("Couldn't store the analysis of cover {n}: {e}") }
+            UpdatingPlaceholderHash(e, h) => { format!("Couldn't update the placeholder hash {h}: {e}") }
+            DrawingMontage(e, n) => { format!("Couldn't draw the covers of cycle {n}: {e}") }
+            InsertingComment(e, n) => { format!("Error inserting comment on summary {n}: {e}") }
+            UpdatingComment(e, id) => { format!("Error updating comment {id}: {e}") }
+            DeletingComment(e, id) => { format!("Error deleting comment {id}: {e}") }
+            UpdatingReading(e, n) => { format!("Error updating reading status of book {n}: {e}") }
+            InsertingEntity(e, name) => { format!("Error inserting entity {name}: {e}") }
+            UpdatingSummaryEntities(e, n) => { format!("Error tagging entities on summary {n}: {e}") }
+            UpdatingReferences(e, n) => { format!("Error updating the references of summary {n}: {e}") }
             Unknown(s) => { format!("Unknown error: {s}") }
         };
 
@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use chrono::{Utc};
+use chrono::{NaiveDate, Utc};
 use serde::Deserialize;
 use tracing::info;
 use uuid::Uuid;
@@ -7,9 +7,10 @@ use crate::constants::{ADMIN, GROUP_EMAIL_ADDRESS, PRODUCTION_HOST};
 use crate::db::Db;
 use crate::email::Email;
 use crate::pages::edit::FormData;
-use crate::entities::{Book, Summary, User};
-use crate::errors::Error::{IncorrectPassword, UnknownUser};
+use crate::entities::{Book, BookEdit, Summary, User};
+use crate::errors::Error::{IncorrectPassword, UnknownUser, UpdatingBook};
 use crate::errors::{DbResult, Error};
+use crate::references::find_references;
 use crate::PerryState;
 
 pub async fn save_summary_logic(state: &PerryState, user: Option<User>, form_data: FormData)
@@ -25,7 +26,9 @@ pub async fn save_summary_logic(state: &PerryState, user: Option<User>, form_dat
     });
 
     let book_number = form_data.number as i32;
+    let series = form_data.series;
     let summary = Summary {
+        series,
         number: book_number,
         author_email: form_data.author_email.clone(),
         author_name: form_data.author_name.clone(),
@@ -38,22 +41,39 @@ pub async fn save_summary_logic(state: &PerryState, user: Option<User>, form_dat
     // Update the book if applicable
     let title = form_data.german_title.clone();
     let author = form_data.book_author.clone();
+    // An empty field clears the date, a missing one keeps the date the book already has
+    let published = match form_data.published.as_deref().map(str::trim) {
+        None | Some("") => { None }
+        Some(d) => {
+            Some(NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                .map_err(|e| UpdatingBook(format!("invalid date \"{d}\": {e}"), book_number))?)
+        }
+    };
     let book = Book {
+        series,
         number: book_number,
         title, author,
+        published,
         german_file: None,
     };
     let book_number = book.number as u32;
     let db = &state.db;
     let username = user.clone().map_or("<unknown>".to_string(), |u| u.email.clone());
+    let login = user.as_ref().map(|u| u.login.clone());
 
     if user.map_or(false, |u| u.can_post()) {
         // User is logged in, save the summary
 
-        // Create the book if it doesn't already exist
-        db.update_or_insert_book(book).await?;
+        // Create the book if it doesn't already exist, the form doesn't have the German file
+        let existing = db.find_book(series, book_number).await;
+        let published = match form_data.published {
+            None => { existing.as_ref().and_then(|b| b.published) }
+            Some(_) => { book.published }
+        };
+        let german_file = existing.and_then(|b| b.german_file);
+        save_book_logic(state, login, Book { german_file, published, ..book }).await?;
 
-        let old_summary = db.find_summary(book_number).await;
+        let old_summary = db.find_summary(series, book_number).await;
         let already_exists = old_summary.is_some();
 
         //
@@ -82,17 +102,22 @@ pub async fn save_summary_logic(state: &PerryState, user: Option<User>, form_dat
         //
         // Update or insert the summary
         //
+        let references = find_references(&summary.summary);
         if already_exists {
             // Summary already exists, update
-            db.update_summary(summary).await
+            db.update_summary(summary).await?;
         } else {
             // New summary
             // Notify the group
             send_summary_to_group(state, &summary).await?;
 
             // Insert
-            db.insert_summary(summary).await
+            db.insert_summary(summary).await?;
         }
+
+        // Keep the backlinks index up to date
+        db.update_references(series, book_number, references).await?;
+        Ok(())
     } else {
         // No user logged in, save that summary in the PENDING table
         info!("No user logged in, saving summary {} in pending", summary.number);
@@ -106,6 +131,46 @@ pub async fn save_summary_logic(state: &PerryState, user: Option<User>, form_dat
     }
 }
 
+/// Save a book and record which of its fields changed in the audit trail
+pub async fn save_book_logic(state: &PerryState, login: Option<String>, book: Book) -> DbResult<()> {
+    let old = state.db.find_book(book.series, book.number as u32).await;
+    let date = Utc::now().naive_local().format("%Y-%m-%d %H:%M").to_string();
+    let edits = book_edits(old.as_ref(), &book, login, date);
+    if old.is_some() && edits.is_empty() {
+        return Ok(());
+    }
+    state.db.update_book_with_edits(book, edits).await
+}
+
+/// The fields that differ between the two versions of a book
+pub fn book_edits(old: Option<&Book>, new: &Book, login: Option<String>, date: String) -> Vec<BookEdit> {
+    fn fields(book: &Book) -> [(&'static str, Option<String>); 4] {
+        let non_empty = |s: &str| Some(s.to_string()).filter(|s| ! s.is_empty());
+        [
+            ("title", non_empty(&book.title)),
+            ("author", non_empty(&book.author)),
+            ("published", book.published.map(|d| d.to_string())),
+            ("german_file", book.german_file.as_deref().and_then(non_empty)),
+        ]
+    }
+    let old_fields = old.map(fields);
+    fields(new).into_iter().enumerate()
+        .filter_map(|(i, (field, new_value))| {
+            let old_value = old_fields.as_ref().and_then(|f| f[i].1.clone());
+            (old_value != new_value).then(|| BookEdit {
+                id: 0,
+                series: new.series,
+                number: new.number,
+                login: login.clone(),
+                field: field.into(),
+                old_value,
+                new_value,
+                date: date.clone(),
+            })
+        })
+        .collect()
+}
+
 pub async fn send_summary_to_group(state: &PerryState, summary: &Summary) -> Result<(), Error> {
     let to = if state.config.is_heroku {
         GROUP_EMAIL_ADDRESS
@@ -133,6 +198,11 @@ pub async fn send_summary_to_group(state: &PerryState, summary: &Summary) -> Res
     }
 }
 
+/// In spoiler-safe mode, everything after the last Heft the reader has read is a spoiler
+pub fn is_spoiler(read_up_to: Option<u32>, book_number: i32) -> bool {
+    read_up_to.is_some_and(|n| book_number > n as i32)
+}
+
 fn verify_password(supplied_password: &str, salt: &Vec<u8>, password: &Vec<u8>) -> bool {
     use sha2::*;
     let r1 = Sha512::new()
@@ -147,6 +217,12 @@ fn verify_password(supplied_password: &str, salt: &Vec<u8>, password: &Vec<u8>)
     success
 }
 
+/// Empty to leave spoiler-safe mode
+#[derive(Deserialize)]
+pub struct ReadUpToFormData {
+    pub read_up_to: String,
+}
+
 #[derive(Deserialize)]
 pub struct LoginFormData {
     pub username: String,
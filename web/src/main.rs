@@ -6,10 +6,13 @@ use tracing::{info};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 use crate::axum::main_axum;
 use crate::config::{Config, create_config};
+use crate::cover_providers::{create_cover_providers, CoverProviders};
+use crate::cover_store::{create_cover_store, CoverStore};
 use crate::db::{create_db, Db};
 use crate::email::{Email, EmailService};
 use crate::entities::User;
-use crate::perrypedia::{CoverFinder, LocalImageProvider};
+use crate::prefetch::{start_cover_prefetch, CoverPrefetch};
+use crate::perrypedia::{BookMetadataFinder, CoverFinder, CycleFinder, LocalImageProvider, PerryPedia};
 
 mod db;
 mod entities;
@@ -24,6 +27,14 @@ mod config;
 mod constants;
 mod test;
 mod covers;
+mod cover_path;
+mod cover_store;
+mod cover_providers;
+mod cover_quality;
+mod placeholder;
+mod montage;
+mod references;
+mod prefetch;
 // mod actix;
 mod axum;
 
@@ -46,13 +57,22 @@ async fn main() -> std::io::Result<()> {
 
     info!("Starting server on port {}, config.database_url: {}", config.port,
         config.database_url.clone().unwrap_or("<none found>".into()));
+    let db: Arc<Box<dyn Db>> = Arc::new(create_db(&config).await);
+    let perry_pedia = PerryPedia::new(&config);
+    let cover_store: Arc<Box<dyn CoverStore>> = Arc::new(create_cover_store(&config, db.clone()));
     let state = PerryState {
         app_name: "Perry Rust".into(),
         config: config.clone(),
-        db: Arc::new(create_db(&config).await),
+        db: db.clone(),
         email_service: Arc::new(Email::create_email_service(&config).await),
         cover_finder: Arc::new(Box::new(LocalImageProvider)),
+        metadata_finder: Arc::new(Box::new(perry_pedia.clone())),
+        cycle_finder: Arc::new(Box::new(perry_pedia.clone())),
+        cover_prefetch: Arc::new(CoverPrefetch::default()),
+        cover_store: cover_store.clone(),
+        cover_providers: Arc::new(create_cover_providers(&config, db.clone(), cover_store, perry_pedia)),
     };
+    start_cover_prefetch(state.clone());
 
     // main_actix(config, state).await
     main_axum(config, state).await
@@ -92,9 +112,16 @@ pub struct PerryState {
     pub db: Arc<Box<dyn Db>>,
     pub email_service: Arc<Box<dyn EmailService>>,
     pub cover_finder: Arc<Box<dyn CoverFinder>>,
+    pub metadata_finder: Arc<Box<dyn BookMetadataFinder>>,
+    pub cycle_finder: Arc<Box<dyn CycleFinder>>,
+    pub cover_prefetch: Arc<CoverPrefetch>,
+    pub cover_store: Arc<Box<dyn CoverStore>>,
+    pub cover_providers: Arc<CoverProviders>,
 }
 
 const COOKIE_AUTH_TOKEN: &str = &"authToken";
+/// Spoiler-safe mode: the last Heft the reader has read
+const COOKIE_READ_UP_TO: &str = "readUpTo";
 
 #[async_trait]
 pub trait CookieManager<T>: Sync {
@@ -103,4 +130,8 @@ pub trait CookieManager<T>: Sync {
     async fn clear_auth_token_cookie(&self) -> T {
         self.create_auth_token_cookie("".into(), 0).await
     }
+    /// Summaries after this number are spoilers
+    async fn find_read_up_to(&self) -> Option<u32>;
+    /// `None` clears the cookie
+    async fn create_read_up_to_cookie(&self, read_up_to: Option<u32>) -> T;
 }
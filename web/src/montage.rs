@@ -0,0 +1,78 @@
+use image::imageops::{overlay, FilterType};
+use image::{DynamicImage, Rgb, RgbImage};
+use tracing::warn;
+use crate::covers::decode_with_min_side;
+use crate::entities::{CoverSize, Series};
+use crate::placeholder::draw_placeholder;
+
+const BACKGROUND: Rgb<u8> = Rgb([15, 39, 87]);
+/// Covers per row
+const COLUMNS: u32 = 10;
+/// Around each cover
+const GAP: u32 = 6;
+
+/// The covers of a cycle side by side, in rows of COLUMNS, each one shrunk into the thumbnail
+/// box. `covers` holds the stored thumbnail of each book, `None` when there is none: a
+/// placeholder takes its place, like it does for the images that can't be decoded.
+pub fn draw_montage(series: Series, cycle: &str, covers: Vec<(i32, Option<Vec<u8>>)>) -> RgbImage {
+    let (width, height) = CoverSize::Thumb.bounds();
+    let count = covers.len().max(1) as u32;
+    let (columns, rows) = (count.min(COLUMNS), count.div_ceil(COLUMNS));
+    let mut montage = RgbImage::from_pixel(GAP + columns * (width + GAP), GAP + rows * (height + GAP), BACKGROUND);
+
+    for (i, (number, image)) in covers.into_iter().enumerate() {
+        let tile = thumbnail(series, number, cycle, image);
+        let (column, row) = (i as u32 % COLUMNS, i as u32 / COLUMNS);
+        // Centered in its box, covers aren't all exactly 2:3
+        let x = GAP + column * (width + GAP) + (width - tile.width()) / 2;
+        let y = GAP + row * (height + GAP) + (height - tile.height()) / 2;
+        overlay(&mut montage, &tile, x as i64, y as i64);
+    }
+
+    montage
+}
+
+fn thumbnail(series: Series, number: i32, cycle: &str, image: Option<Vec<u8>>) -> RgbImage {
+    let (width, height) = CoverSize::Thumb.bounds();
+    // With the checks of the uploads, thumbnails are just smaller
+    let decoded = image.and_then(|bytes| match decode_with_min_side(number as u32, &bytes, 1) {
+        Ok(img) => { Some(img) }
+        Err(e) => {
+            warn!("Couldn't decode the cover of {series} {number} for the montage: {e}");
+            None
+        }
+    });
+    let img = decoded.unwrap_or_else(|| {
+        DynamicImage::ImageRgb8(draw_placeholder(series, number as u32, Some(cycle)))
+    });
+    if img.width() <= width && img.height() <= height {
+        img.to_rgb8()
+    } else {
+        img.resize(width, height, FilterType::Triangle).to_rgb8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::Cursor;
+    use crate::entities::Series;
+    use crate::montage::{draw_montage, BACKGROUND, GAP};
+
+    #[test]
+    fn rows_of_ten() {
+        let mut red = Cursor::new(Vec::new());
+        RgbImage::from_pixel(120, 168, Rgb([255, 0, 0])).write_to(&mut red, ImageFormat::Png).unwrap();
+        let mut covers: Vec<(i32, Option<Vec<u8>>)> = (1..=23).map(|n| (n, None)).collect();
+        covers[0].1 = Some(red.into_inner());
+        covers[1].1 = Some(b"not an image".to_vec());
+
+        let montage = draw_montage(Series::Pr, "Die Dritte Macht", covers);
+        assert_eq!(montage.dimensions(), (GAP + 10 * (120 + GAP), GAP + 3 * (180 + GAP)));
+        // The first cover is 168 pixels high, centered in a 180 pixels box
+        assert_eq!(*montage.get_pixel(GAP + 60, GAP + 90), Rgb([255, 0, 0]));
+        assert_eq!(*montage.get_pixel(GAP + 60, GAP + 2), BACKGROUND);
+        // The last row only has three covers
+        assert_eq!(*montage.get_pixel(montage.width() - GAP - 60, montage.height() - GAP - 90), BACKGROUND);
+    }
+}
@@ -0,0 +1,172 @@
+use askama::Template;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+use crate::entities::{Book, BookEdit, Series};
+use crate::errors::{Error, PrResult, PrResultBuilder};
+use crate::logic::save_book_logic;
+use crate::url::Urls;
+use crate::{CookieManager, PerryState};
+
+/// The German metadata of a book, which can be edited whether it has a summary or not
+#[derive(Deserialize)]
+pub struct BookFormData {
+    pub title: String,
+    pub author: String,
+    /// yyyy-mm-dd, empty if unknown
+    #[serde(default)]
+    pub published: String,
+    #[serde(default)]
+    pub german_file: String,
+}
+
+impl BookFormData {
+    /// Fails if the date isn't empty and can't be parsed
+    fn into_book(self, series: Series, number: u32) -> Result<Book, Error> {
+        let published = match self.published.trim() {
+            "" => { None }
+            d => {
+                Some(NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                    .map_err(|_| Error::UpdatingBook("invalid date".into(), number as i32))?)
+            }
+        };
+        Ok(Book {
+            series,
+            number: number as i32,
+            title: self.title.trim().into(),
+            author: self.author.trim().into(),
+            published,
+            german_file: Some(self.german_file.trim().to_string()).filter(|f| ! f.is_empty()),
+        })
+    }
+}
+
+#[derive(Template)]
+#[template(path = "edit_book.html")]
+struct TemplateEditBook {
+    book: Book,
+    /// Where the form posts to
+    href: String,
+    href_summary: String,
+    edits: Vec<BookEdit>,
+}
+
+async fn find_book_or_new(state: &PerryState, series: Series, number: u32) -> Book {
+    state.db.find_book(series, number).await.unwrap_or(Book {
+        series,
+        number: number as i32,
+        ..Default::default()
+    })
+}
+
+pub async fn edit_book_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32)
+    -> PrResult
+{
+    if ! cookie_manager.find_user(state.db.clone()).await.is_some_and(|u| u.is_editor()) {
+        return PrResultBuilder::root();
+    }
+
+    let template = TemplateEditBook {
+        book: find_book_or_new(state, series, number).await,
+        href: format!("{}/edit", Urls::book(series, number as i32)),
+        href_summary: Urls::summary(series, number as i32),
+        edits: state.db.find_book_edits(series, Some(number)).await,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+pub async fn post_edit_book_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32, form: BookFormData)
+    -> PrResult
+{
+    save_book(state, cookie_manager, series, number, form).await?;
+    PrResultBuilder::redirect(format!("{}/edit", Urls::book(series, number as i32)))
+}
+
+/// A book and its audit trail
+pub async fn api_book_logic(state: &PerryState, series: Series, number: u32) -> PrResult {
+    let (book, edits) = tokio::join!(
+        state.db.find_book(series, number),
+        state.db.find_book_edits(series, Some(number)),
+    );
+    let book = book.ok_or(Error::FetchingBook("no such book".into(), number))?;
+    PrResultBuilder::json(serde_json::to_string(&json!({ "book": book, "edits": edits })).unwrap())
+}
+
+/// Update a book and return its new version
+pub async fn api_post_book_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32, form: BookFormData)
+    -> PrResult
+{
+    let book = save_book(state, cookie_manager, series, number, form).await?;
+    PrResultBuilder::json(serde_json::to_string(&json!(book)).unwrap())
+}
+
+async fn save_book<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32, form: BookFormData)
+    -> Result<Book, Error>
+{
+    let user = cookie_manager.find_user(state.db.clone()).await.filter(|u| u.is_editor());
+    let Some(user) = user else {
+        warn!("Rejecting the edit of book {series} {number}: not an editor");
+        return Err(Error::UpdatingBook("only editors can edit books".into(), number as i32));
+    };
+
+    let book = form.into_book(series, number)?;
+    if book.title.is_empty() {
+        return Err(Error::UpdatingBook("the title can't be empty".into(), number as i32));
+    }
+    info!("{} editing book {series} {number}", user.login);
+    save_book_logic(state, Some(user.login), book.clone()).await?;
+    Ok(book)
+}
+
+#[derive(Template)]
+#[template(path = "books_audit.html")]
+struct TemplateBooksAudit {
+    series: Series,
+    /// "/neo" for the URL's of that series
+    prefix: String,
+    edits: Vec<BookEdit>,
+}
+
+/// The most recent changes made to the books of a series
+pub async fn books_audit_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series)
+    -> PrResult
+{
+    if ! cookie_manager.find_user(state.db.clone()).await.is_some_and(|u| u.is_editor()) {
+        return PrResultBuilder::root();
+    }
+
+    let template = TemplateBooksAudit {
+        series,
+        prefix: Urls::series(series),
+        edits: state.db.find_book_edits(series, None).await,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::entities::Series;
+    use crate::errors::Error;
+    use crate::pages::books::BookFormData;
+
+    fn form(published: &str) -> BookFormData {
+        BookFormData { title: " Unternehmen Stardust ".into(), author: "K.H. Scheer".into(),
+            published: published.into(), german_file: "".into() }
+    }
+
+    #[test]
+    fn dates() {
+        let book = form("1961-09-08").into_book(Series::Pr, 1).unwrap();
+        assert_eq!((book.title.as_str(), book.published), ("Unternehmen Stardust", NaiveDate::from_ymd_opt(1961, 9, 8)));
+        assert_eq!(form(" ").into_book(Series::Pr, 1).unwrap().published, None);
+        assert!(matches!(form("8.9.1961").into_book(Series::Pr, 1),
+            Err(Error::UpdatingBook(e, 1)) if e == "invalid date"));
+    }
+}
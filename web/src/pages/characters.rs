@@ -0,0 +1,269 @@
+use askama::Template;
+use regex::{escape, Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::banner_info::BannerInfo;
+use crate::entities::{Appearance, Entity, EntityKind, Series, User};
+use crate::errors::{PrResult, PrResultBuilder};
+use crate::url::Urls;
+use crate::{CookieManager, PerryState};
+
+/// What the summary JSON carries for each tagged entity
+#[derive(Default, Deserialize, Serialize)]
+pub struct TemplateEntity {
+    pub name: String,
+    pub href: String,
+}
+
+impl From<Entity> for TemplateEntity {
+    fn from(entity: Entity) -> Self {
+        Self {
+            href: Urls::character(&entity.slug),
+            name: entity.name,
+        }
+    }
+}
+
+/// "Perry Rhodan" -> "perry-rhodan", "Gäa" -> "gaea"
+pub fn slugify(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        match c {
+            'ä' => result.push_str("ae"),
+            'ö' => result.push_str("oe"),
+            'ü' => result.push_str("ue"),
+            'ß' => result.push_str("ss"),
+            c if c.is_ascii_alphanumeric() => result.push(c),
+            _ => {
+                if ! result.is_empty() && ! result.ends_with('-') {
+                    result.push('-');
+                }
+            }
+        }
+    }
+    result.trim_end_matches('-').to_string()
+}
+
+/// The ids of the entities whose name or one of the aliases appears in the text (ignoring case).
+/// All the names are compiled once, in a single RegexSet.
+pub fn suggest_entities(text: &str, entities: &[Entity]) -> Vec<i32> {
+    let text = Regex::new("<[^>]*>").unwrap().replace_all(text, " ");
+    let mut ids = Vec::new();
+    let mut patterns = Vec::new();
+    for entity in entities {
+        for alias in std::iter::once(&entity.name).chain(entity.aliases.iter()) {
+            if ! alias.trim().is_empty() {
+                // Not \b, which needs a letter or digit on the inside ("Tolot (Haluter)")
+                patterns.push(format!(r"(?i)(?:^|\W){}(?:\W|$)", escape(alias.trim())));
+                ids.push(entity.id);
+            }
+        }
+    }
+    let Ok(set) = RegexSet::new(&patterns) else {
+        warn!("Couldn't compile the names of the entities");
+        return Vec::new();
+    };
+    let mut result: Vec<i32> = set.matches(&text).into_iter().map(|i| ids[i]).collect();
+    result.dedup();
+    result
+}
+
+//
+// /characters/{slug}
+//
+
+#[derive(Template)]
+#[template(path = "character.html")]
+struct TemplateCharacter {
+    banner_info: BannerInfo,
+    entity: Entity,
+    appearances: Vec<TemplateAppearance>,
+}
+
+struct TemplateAppearance {
+    appearance: Appearance,
+    href: String,
+}
+
+pub async fn character_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        slug: String)
+    -> PrResult
+{
+    match state.db.find_entity(&slug).await {
+        Some(entity) => {
+            let appearances = state.db.find_appearances(entity.id).await.into_iter()
+                .map(|appearance| TemplateAppearance {
+                    href: Urls::summary(appearance.series, appearance.number),
+                    appearance,
+                })
+                .collect();
+            let template = TemplateCharacter {
+                banner_info: BannerInfo::new(cookie_manager.find_user(state.db.clone()).await).await,
+                entity,
+                appearances,
+            };
+            PrResultBuilder::html(template.render().unwrap())
+        }
+        None => {
+            warn!("Unknown character: {slug}");
+            PrResultBuilder::not_found()
+        }
+    }
+}
+
+//
+// Tagging a summary
+//
+
+struct TemplateTag {
+    entity: Entity,
+    checked: bool,
+    suggested: bool,
+}
+
+#[derive(Template)]
+#[template(path = "summary_entities.html")]
+struct TemplateSummaryEntities {
+    series: Series,
+    number: u32,
+    href_summary: String,
+    href_entities: String,
+    english_title: String,
+    tags: Vec<TemplateTag>,
+}
+
+async fn find_editor<T>(state: &PerryState, cookie_manager: impl CookieManager<T>) -> Option<User> {
+    cookie_manager.find_user(state.db.clone()).await.filter(|u| u.is_editor())
+}
+
+pub async fn summary_entities_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    if find_editor(state, cookie_manager).await.is_none() {
+        return PrResultBuilder::redirect(Urls::summary(series, book_number as i32));
+    }
+
+    let (summary, entities, tagged) = tokio::join!(
+        state.db.find_summary(series, book_number),
+        state.db.fetch_entities(),
+        state.db.find_summary_entities(series, book_number),
+    );
+    let (english_title, text) = summary.map_or(("".into(), "".into()),
+        |s| (s.english_title, s.summary));
+    let suggestions = suggest_entities(&text, &entities);
+
+    // Suggestions first, then the entities already tagged, then everything else.
+    // Suggestions are only pre-checked until the summary has been tagged once.
+    let mut tags: Vec<TemplateTag> = entities.into_iter().map(|entity| {
+        let suggested = suggestions.contains(&entity.id);
+        TemplateTag {
+            checked: tagged.iter().any(|t| t.id == entity.id) || (tagged.is_empty() && suggested),
+            suggested,
+            entity,
+        }
+    }).collect();
+    tags.sort_by_key(|t| (! t.suggested, ! t.checked));
+
+    let template = TemplateSummaryEntities {
+        series,
+        number: book_number,
+        href_summary: Urls::summary(series, book_number as i32),
+        href_entities: Urls::summary_entities(series, book_number as i32),
+        english_title,
+        tags,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+/// The form posts one `entity` field per checked box
+pub async fn post_summary_entities_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32, form: Vec<(String, String)>)
+    -> PrResult
+{
+    if let Some(user) = find_editor(state, cookie_manager).await {
+        let ids: Vec<i32> = form.iter()
+            .filter(|(key, _)| key == "entity")
+            .filter_map(|(_, value)| value.parse::<i32>().ok())
+            .collect();
+        info!("{} tagging summary {series} {book_number} with {ids:?}", user.login);
+        state.db.update_summary_entities(series, book_number, ids).await?;
+    }
+
+    PrResultBuilder::redirect(Urls::summary(series, book_number as i32))
+}
+
+#[derive(Deserialize)]
+pub struct EntityFormData {
+    pub name: String,
+    pub kind: EntityKind,
+    /// Comma separated
+    pub aliases: String,
+    /// The summary being tagged when this entity was created
+    #[serde(default)]
+    pub series: Series,
+    pub number: u32,
+}
+
+pub async fn insert_entity_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        form: EntityFormData)
+    -> PrResult
+{
+    let slug = slugify(&form.name);
+    if find_editor(state, cookie_manager).await.is_some() && ! slug.is_empty() {
+        state.db.insert_entity(Entity {
+            slug,
+            name: form.name.trim().to_string(),
+            kind: form.kind,
+            aliases: form.aliases.split(',')
+                .map(|a| a.trim().to_string())
+                .filter(|a| ! a.is_empty())
+                .collect(),
+            ..Default::default()
+        }).await?;
+    }
+
+    PrResultBuilder::redirect(Urls::summary_entities(form.series, form.number as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::{Entity, EntityKind};
+    use crate::pages::characters::{slugify, suggest_entities};
+
+    fn entity(id: i32, name: &str, aliases: &[&str]) -> Entity {
+        Entity {
+            id,
+            slug: slugify(name),
+            name: name.into(),
+            kind: EntityKind::Character,
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("Perry Rhodan"), "perry-rhodan");
+        assert_eq!(slugify("  Gäa "), "gaea");
+        assert_eq!(slugify("Icho Tolot (Haluter)"), "icho-tolot-haluter");
+        assert_eq!(slugify("Straße der Öde!"), "strasse-der-oede");
+    }
+
+    #[test]
+    fn suggestions() {
+        let entities = vec![
+            entity(1, "Perry Rhodan", &["Rhodan"]),
+            entity(2, "Gäa", &[]),
+            entity(3, "Icho Tolot", &["Tolot (Haluter)", "Tolot?"]),
+            entity(4, "ES", &["   "]),
+        ];
+        assert_eq!(suggest_entities("<p>RHODAN lands on <b>gäa</b>.</p>", &entities), vec![1, 2]);
+        // A longer word isn't the name
+        assert!(suggest_entities("The Gäaner and the Rhodanites", &entities).is_empty());
+        // Metacharacters are taken literally
+        assert_eq!(suggest_entities("Tolot (Haluter) laughs", &entities), vec![3]);
+        assert!(suggest_entities("Tolot Haluter", &entities).is_empty());
+        assert_eq!(suggest_entities("Is it Tolot?", &entities), vec![3]);
+        assert_eq!(suggest_entities("es", &entities), vec![4]);
+    }
+}
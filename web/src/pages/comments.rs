@@ -0,0 +1,277 @@
+use askama::Template;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::constants::PRODUCTION_HOST;
+use crate::email::Email;
+use crate::entities::{Comment, CommentStatus, Series};
+use crate::errors::{PrResult, PrResultBuilder};
+use crate::url::Urls;
+use crate::{CookieManager, PerryState};
+
+/// Posted from the comment form under each summary
+#[derive(Deserialize)]
+pub struct CommentFormData {
+    pub parent_id: Option<String>,
+    pub text: String,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+}
+
+/// What the Vue front end receives. The email address of the commenter is never exposed.
+#[derive(Deserialize, Serialize)]
+struct TemplateComment {
+    id: i32,
+    parent_id: Option<i32>,
+    author_name: String,
+    text: String,
+    date: String,
+    depth: usize,
+}
+
+/// Flatten the comments in thread order: each comment is followed by its replies,
+/// with `depth` telling the front end how far to indent it. Replies whose parent
+/// is not visible (e.g. still in moderation) are shown at the top level.
+fn thread_comments(comments: &[Comment]) -> Vec<TemplateComment> {
+    fn add(comments: &[Comment], comment: &Comment, depth: usize, result: &mut Vec<TemplateComment>) {
+        result.push(TemplateComment {
+            id: comment.id,
+            parent_id: comment.parent_id,
+            author_name: comment.author_name.clone(),
+            text: comment.text.clone(),
+            date: comment.date.clone(),
+            depth,
+        });
+        for reply in comments.iter().filter(|c| c.parent_id == Some(comment.id)) {
+            add(comments, reply, depth + 1, result);
+        }
+    }
+
+    let mut result = Vec::new();
+    for comment in comments.iter().filter(|c| {
+        c.parent_id.is_none_or(|p| ! comments.iter().any(|other| other.id == p))
+    }) {
+        add(comments, comment, 0, &mut result);
+    }
+
+    result
+}
+
+pub async fn api_comments_logic(state: &PerryState, series: Series, book_number: u32) -> PrResult {
+    let comments = state.db.find_comments(series, book_number, CommentStatus::Approved).await;
+    PrResultBuilder::json(serde_json::to_string(&json!(thread_comments(&comments))).unwrap())
+}
+
+pub async fn post_comment_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32, form: CommentFormData)
+    -> PrResult
+{
+    let redirect = PrResultBuilder::redirect(Urls::summary(series, book_number as i32));
+    let text = form.text.trim().to_string();
+    if text.is_empty() {
+        return redirect;
+    }
+
+    if state.db.find_summary(series, book_number).await.is_none() {
+        warn!("Comment on {series} {book_number}, which has no summary, ignoring it");
+        return redirect;
+    }
+    // Replies only go to approved comments of the same summary
+    let parent_id = match form.parent_id.as_deref().map(str::trim).filter(|p| ! p.is_empty()) {
+        None => { None }
+        Some(p) => {
+            let parent = match p.parse::<i32>() {
+                Ok(id) => { state.db.find_comment(id).await }
+                Err(_) => { None }
+            };
+            match parent {
+                Some(parent) if parent.series == series && parent.number == book_number as i32
+                        && parent.status == CommentStatus::Approved => {
+                    Some(parent.id)
+                }
+                _ => {
+                    warn!("Reply on {series} {book_number} to unknown comment {p}, ignoring it");
+                    return redirect;
+                }
+            }
+        }
+    };
+    let date = Utc::now().naive_local().format("%Y-%m-%d %H:%M").to_string();
+    let user = cookie_manager.find_user(state.db.clone()).await;
+    let comment = match user {
+        Some(user) => {
+            // Logged in users skip the email verification, admins skip moderation too
+            let status = if user.is_admin() { CommentStatus::Approved } else { CommentStatus::Pending };
+            Comment {
+                series,
+                number: book_number as i32,
+                parent_id,
+                login: Some(user.login.clone()),
+                author_name: user.name.clone(),
+                author_email: user.email.clone(),
+                text,
+                status,
+                date,
+                ..Default::default()
+            }
+        }
+        None => {
+            let author_email = form.author_email.unwrap_or_default().trim().to_string();
+            if ! author_email.contains('@') {
+                warn!("Anonymous comment on {series} {book_number} without a valid email address, ignoring it");
+                return redirect;
+            }
+            Comment {
+                series,
+                number: book_number as i32,
+                parent_id,
+                author_name: form.author_name.unwrap_or_default().trim().to_string(),
+                author_email,
+                text,
+                status: CommentStatus::Unverified,
+                verification_token: Some(Uuid::new_v4().to_string()),
+                date,
+                ..Default::default()
+            }
+        }
+    };
+
+    state.db.insert_comment(comment.clone()).await?;
+
+    match (&comment.status, &comment.verification_token) {
+        (CommentStatus::Unverified, Some(token)) => {
+            send_verification_email(state, &comment, token);
+        }
+        _ => {
+            comment_posted(state, &comment).await;
+        }
+    }
+
+    redirect
+}
+
+/// Anonymous comments only enter the moderation queue once their author clicked this link
+pub async fn verify_comment_logic(state: &PerryState, token: String) -> PrResult {
+    match state.db.find_comment_by_token(&token).await {
+        Some(mut comment) if comment.status == CommentStatus::Unverified => {
+            info!("Verified comment {} on summary {} {}", comment.id, comment.series, comment.number);
+            state.db.update_comment_status(comment.id, CommentStatus::Pending).await?;
+            comment.status = CommentStatus::Pending;
+            comment_posted(state, &comment).await;
+            PrResultBuilder::redirect(Urls::summary(comment.series, comment.number))
+        }
+        _ => {
+            warn!("Unknown comment verification token: {token}");
+            PrResultBuilder::root()
+        }
+    }
+}
+
+fn send_verification_email(state: &PerryState, comment: &Comment, token: &str) {
+    let url = format!("https://{PRODUCTION_HOST}{}", Urls::verify_comment(token));
+    let body = format!("Thank you for your comment on summary {}.<br>\
+            Please confirm your email address by clicking <a href=\"{url}\">this link</a>.",
+        comment.number);
+    if let Err(e) = state.email_service.send_email(&comment.author_email,
+            &format!("Please confirm your comment on summary {}", comment.number), &body) {
+        error!("Couldn't send verification email for comment on {} {}: {e}", comment.series, comment.number);
+    }
+}
+
+/// What the commenters typed goes into the HTML of the notification emails
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Notify the author of the summary and the moderators
+async fn comment_posted(state: &PerryState, comment: &Comment) {
+    let (series, number) = (comment.series, comment.number);
+    let body = format!("Comment by {}:<br>{}<br><br>\
+            <a href=\"https://{PRODUCTION_HOST}{}\">Summary {number}</a>",
+        escape_html(&comment.author_name), escape_html(&comment.text), Urls::summary(series, number));
+
+    if let Some(summary) = state.db.find_summary(series, number as u32).await {
+        if ! summary.author_email.is_empty() && summary.author_email != comment.author_email {
+            if let Err(e) = state.email_service.send_email(&summary.author_email,
+                    &format!("New comment on your summary {number}: {}", summary.english_title),
+                    &body) {
+                error!("Couldn't notify the author of summary {series} {number}: {e}");
+            }
+        }
+    }
+
+    if comment.status == CommentStatus::Pending {
+        Email::notify_admin(state, &format!("New comment on summary {number} awaiting moderation"),
+            &format!("{body}<br><a href=\"https://{PRODUCTION_HOST}{}\">Moderation queue</a>",
+                Urls::comments_moderation())).await;
+    }
+}
+
+//
+// Moderation
+//
+
+#[derive(Template)]
+#[template(path = "comments.html")]
+struct TemplateModeration {
+    comments: Vec<TemplateModeratedComment>,
+}
+
+struct TemplateModeratedComment {
+    comment: Comment,
+    /// The summary it's about
+    href: String,
+}
+
+pub async fn moderation_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+    -> PrResult
+{
+    match cookie_manager.find_user(state.db.clone()).await {
+        Some(u) if u.is_admin() => {
+            let template = TemplateModeration {
+                comments: state.db.find_comments_by_status(CommentStatus::Pending).await.into_iter()
+                    .map(|comment| TemplateModeratedComment {
+                        href: Urls::summary(comment.series, comment.number),
+                        comment,
+                    })
+                    .collect(),
+            };
+            PrResultBuilder::html(template.render().unwrap())
+        }
+        _ => {
+            PrResultBuilder::root()
+        }
+    }
+}
+
+pub async fn approve_comment_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        id: i32)
+    -> PrResult
+{
+    match cookie_manager.find_user(state.db.clone()).await {
+        Some(u) if u.is_admin() => {
+            state.db.update_comment_status(id, CommentStatus::Approved).await?;
+            PrResultBuilder::redirect(Urls::comments_moderation())
+        }
+        _ => {
+            PrResultBuilder::root()
+        }
+    }
+}
+
+pub async fn delete_comment_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        id: i32)
+    -> PrResult
+{
+    match cookie_manager.find_user(state.db.clone()).await {
+        Some(u) if u.is_admin() => {
+            state.db.delete_comment(id).await?;
+            PrResultBuilder::redirect(Urls::comments_moderation())
+        }
+        _ => {
+            PrResultBuilder::root()
+        }
+    }
+}
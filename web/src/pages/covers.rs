@@ -0,0 +1,226 @@
+use askama::Template;
+use crate::{CookieManager, PerryState};
+use crate::cover_providers::CoverProviderStats;
+use crate::cover_quality::{analyze_stored_covers, find_problems, CoverProblems};
+use crate::covers::{delete_cover, refetch_cover, store_manual_cover, MAX_COVER_BYTES};
+use crate::entities::{Cover, CoverFetchFailure, CoverSize, CoverSource, CoverStats, PlaceholderHash, Series};
+use crate::errors::{PrResult, PrResultBuilder};
+use crate::pages::cycles::is_admin;
+use crate::prefetch::PrefetchProgress;
+use crate::url::Urls;
+
+#[derive(Template)]
+#[template(path = "covers_prefetch.html")]
+struct TemplateCoverPrefetch {
+    progress: PrefetchProgress,
+    providers: Vec<CoverProviderStats>,
+    stats: Vec<CoverStats>,
+    failures: Vec<CoverFetchFailure>,
+}
+
+impl TemplateCoverPrefetch {
+    fn percentage(&self, s: &CoverStats) -> i64 {
+        if s.books == 0 { 100 } else { (s.covers - s.missing_urls) * 100 / s.books }
+    }
+
+    fn upload_url(&self, f: &CoverFetchFailure) -> String {
+        format!("{}/upload", Urls::cover(f.series, f.number))
+    }
+
+    fn pretty(&self, date: &Option<chrono::DateTime<chrono::Utc>>) -> String {
+        date.map_or("".into(), |d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+    }
+}
+
+/// What the background cover prefetch and the cover providers have done so far, and the covers
+/// that couldn't be downloaded
+pub async fn cover_prefetch_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let (stats, failures) = tokio::join!(
+        state.db.fetch_cover_stats(),
+        state.db.find_cover_fetch_failures(),
+    );
+    let template = TemplateCoverPrefetch {
+        progress: state.cover_prefetch.progress(),
+        providers: state.cover_providers.stats(),
+        stats,
+        failures,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+#[derive(Template)]
+#[template(path = "cover_upload.html")]
+struct TemplateCoverUpload {
+    series: Series,
+    number: u32,
+    /// Includes the hash of the cover so that the browser doesn't show the one it cached
+    cover_url: Option<String>,
+    source: Option<CoverSource>,
+    href_upload: String,
+    href_summary: String,
+    max_megabytes: usize,
+    error: Option<String>,
+}
+
+async fn render_cover_upload(state: &PerryState, series: Series, book_number: u32, error: Option<String>)
+    -> PrResult
+{
+    let cover = state.db.find_cover(series, book_number).await;
+    let n = book_number as i32;
+    let template = TemplateCoverUpload {
+        series,
+        number: book_number,
+        cover_url: cover.as_ref().map(|c| format!("{}&v={}", Urls::cover_sized(series, n, CoverSize::Medium),
+            c.hash.clone().unwrap_or_default())),
+        source: cover.map(|c| c.source),
+        href_upload: format!("{}/upload", Urls::cover(series, n)),
+        href_summary: Urls::summary(series, n),
+        max_megabytes: MAX_COVER_BYTES / 1024 / 1024,
+        error,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+/// Form letting an admin replace the cover of a book
+pub async fn cover_upload_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    render_cover_upload(state, series, book_number, None).await
+}
+
+/// `image` is the uploaded file, or why it couldn't be read from the request
+pub async fn post_cover_upload_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32, image: Result<Vec<u8>, String>)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let result = match image {
+        Ok(bytes) => { store_manual_cover(state, series, book_number, bytes).await.map_err(|e| e.to_string()) }
+        Err(e) => { Err(e) }
+    };
+    match result {
+        Ok(()) => { PrResultBuilder::redirect(format!("{}/upload", Urls::cover(series, book_number as i32))) }
+        Err(e) => { render_cover_upload(state, series, book_number, Some(e)).await }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "covers_report.html")]
+struct TemplateCoverReport {
+    problems: CoverProblems,
+    placeholder_hashes: Vec<PlaceholderHash>,
+    analyzed: usize,
+    not_analyzed: usize,
+}
+
+impl TemplateCoverReport {
+    fn thumb_url(&self, c: &Cover) -> String {
+        format!("{}&v={}", Urls::cover_sized(c.series, c.number, CoverSize::Thumb), c.hash.clone().unwrap_or_default())
+    }
+
+    /// "refetch", "delete" or "placeholder"
+    fn action_url(&self, c: &Cover, action: &str) -> String {
+        format!("{}/{action}", Urls::cover(c.series, c.number))
+    }
+
+    fn summary_url(&self, c: &Cover) -> String {
+        Urls::summary(c.series, c.number)
+    }
+
+    fn dimensions(&self, c: &Cover) -> String {
+        format!("{}x{}", c.width.unwrap_or_default(), c.height.unwrap_or_default())
+    }
+
+    fn source(&self, c: &Cover) -> String {
+        format!("{:?}", c.source)
+    }
+
+    fn hex(&self, phash: &i64) -> String {
+        format!("{:016x}", *phash as u64)
+    }
+
+    fn forget_url(&self, p: &PlaceholderHash) -> String {
+        format!("/covers/placeholders/{}/delete", p.phash)
+    }
+}
+
+/// The stored covers that are probably wrong: the same picture on several books, tiny images
+/// and known placeholders. Each visit also analyzes some of the covers stored before the
+/// report existed.
+pub async fn cover_report_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>) -> PrResult {
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let (mut covers, placeholder_hashes) = tokio::join!(
+        state.db.find_covers(),
+        state.db.find_placeholder_hashes(),
+    );
+    let not_analyzed = analyze_stored_covers(state, &mut covers).await;
+    let hashes: Vec<i64> = placeholder_hashes.iter().map(|p| p.phash).collect();
+    let template = TemplateCoverReport {
+        problems: find_problems(&covers, &hashes),
+        placeholder_hashes,
+        analyzed: covers.len() - not_analyzed,
+        not_analyzed,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+/// Replace a reported cover with the one the providers find now
+pub async fn refetch_cover_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    if is_admin(state, cookie_manager).await {
+        // The failure shows on the prefetch page
+        let _ = refetch_cover(state, series, book_number).await;
+    }
+    PrResultBuilder::redirect(Urls::cover_report())
+}
+
+pub async fn report_delete_cover_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    if is_admin(state, cookie_manager).await {
+        delete_cover(state, series, book_number).await;
+    }
+    PrResultBuilder::redirect(Urls::cover_report())
+}
+
+/// Remember the picture of that cover as a placeholder, so that the books showing it are reported
+pub async fn mark_placeholder_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    if is_admin(state, cookie_manager).await {
+        if let Some(phash) = state.db.find_cover(series, book_number).await.and_then(|c| c.phash) {
+            let _ = state.db.insert_placeholder_hash(phash, series, book_number).await;
+        }
+    }
+    PrResultBuilder::redirect(Urls::cover_report())
+}
+
+pub async fn forget_placeholder_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>, phash: i64)
+    -> PrResult
+{
+    if is_admin(state, cookie_manager).await {
+        let _ = state.db.delete_placeholder_hash(phash).await;
+    }
+    PrResultBuilder::redirect(Urls::cover_report())
+}
@@ -1,21 +1,144 @@
 use askama::Template;
+use futures::{stream, StreamExt};
+use sha2::{Digest, Sha256};
 use crate::banner_info::BannerInfo;
+use crate::covers::{encode, etag_matches};
+use crate::entities::{CoverFormat, CoverKey, CoverSize, Series};
+use crate::errors::Error::DrawingMontage;
 use crate::errors::{PrResult, PrResultBuilder};
+use crate::montage::draw_montage;
+use crate::pages::cycles::{find_cycle_data, TemplateBook, TemplateCycle};
+use crate::url::Urls;
 use crate::{CookieManager, PerryState};
 
 #[derive(Template)]
 #[template(path = "cycle.html")]
-struct TemplateCycle {
+struct TemplateCyclePage {
     pub banner_info: BannerInfo,
+    pub result: TemplateCycle,
+    pub href_edit: String,
+    pub href_covers: String,
+    pub href_previous: String,
+    pub href_next: String,
 }
 
-pub async fn cycle_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+pub async fn cycle_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32)
     -> PrResult
 {
-    let template = TemplateCycle {
-        banner_info: BannerInfo::new(cookie_manager.find_user(state.db.clone()).await).await,
+    let banner_info = BannerInfo::new(cookie_manager.find_user(state.db.clone()).await).await;
+    let result = find_cycle_data(state, cookie_manager, series, number).await?;
+    let number = number as i32;
+    let template = TemplateCyclePage {
+        banner_info,
+        result,
+        href_edit: format!("{}/edit", Urls::cycles(series, number)),
+        href_covers: Urls::cycle_covers(series, number),
+        href_previous: Urls::cycles(series, (number - 1).max(1)),
+        href_next: Urls::cycles(series, number + 1),
     };
 
     PrResultBuilder::html(template.render().unwrap())
 }
 
+#[derive(Template)]
+#[template(path = "cycle_covers.html")]
+struct TemplateCycleCovers {
+    pub banner_info: BannerInfo,
+    pub result: TemplateCycle,
+    pub series: Series,
+    pub href_cycle: String,
+    pub href_montage: String,
+    /// What the browser saves the montage as
+    pub montage_file: String,
+    pub href_previous: String,
+    pub href_next: String,
+}
+
+impl TemplateCycleCovers {
+    fn thumb_url(&self, book: &TemplateBook) -> String {
+        Urls::cover_sized(self.series, book.book.number, CoverSize::Thumb)
+    }
+
+    /// For the screens with more than one pixel per CSS pixel
+    fn medium_url(&self, book: &TemplateBook) -> String {
+        Urls::cover_sized(self.series, book.book.number, CoverSize::Medium)
+    }
+}
+
+/// All the covers of a cycle, each one linking to its summary
+pub async fn cycle_covers_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32)
+    -> PrResult
+{
+    let banner_info = BannerInfo::new(cookie_manager.find_user(state.db.clone()).await).await;
+    let result = find_cycle_data(state, cookie_manager, series, number).await?;
+    let number = number as i32;
+    let template = TemplateCycleCovers {
+        banner_info,
+        result,
+        series,
+        href_cycle: Urls::cycles(series, number),
+        href_montage: format!("{}/montage", Urls::cycle_covers(series, number)),
+        montage_file: format!("{}-cycle-{number}-covers.jpg", series.code().to_lowercase()),
+        href_previous: Urls::cycle_covers(series, (number - 1).max(1)),
+        href_next: Urls::cycle_covers(series, number + 1),
+    };
+
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+/// How many covers of a montage are read at the same time
+const MONTAGE_CONCURRENCY: usize = 8;
+
+/// One JPEG with the covers of the whole cycle, made from the stored thumbnails. The books
+/// without one get a placeholder, and the montage is then only cached as long as a placeholder.
+/// Otherwise its ETag is derived from the hashes of the covers, like the ETag of each cover.
+pub async fn cycle_montage_logic(state: &PerryState, series: Series, number: u32,
+        if_none_match: Option<String>)
+    -> PrResult
+{
+    let Ok(cycle) = state.db.find_cycle(series, number).await else {
+        return PrResultBuilder::not_found();
+    };
+    let numbers: Vec<i32> = state.db.find_books(series, number).await.unwrap_or_default()
+        .iter().map(|b| b.number).collect();
+    let title = if cycle.english_title.is_empty() { cycle.german_title } else { cycle.english_title };
+
+    let hashes: Vec<Option<String>> = stream::iter(numbers.clone())
+        .map(|n| state.db.find_cover_hash(series, n as u32))
+        .buffered(MONTAGE_CONCURRENCY)
+        .collect()
+        .await;
+    // The title is drawn on the placeholders
+    let mut hasher = Sha256::new();
+    hasher.update(&title);
+    for (n, hash) in numbers.iter().zip(&hashes) {
+        hasher.update(format!("|{n}:{}", hash.as_deref().unwrap_or("")));
+    }
+    let etag = format!("\"montage-{:x}\"", hasher.finalize());
+    let complete = hashes.iter().all(Option::is_some);
+    if complete && if_none_match.is_some_and(|h| etag_matches(&h, &etag)) {
+        return PrResultBuilder::not_modified(etag);
+    }
+
+    let covers: Vec<(i32, Option<Vec<u8>>)> = stream::iter(numbers)
+        .map(|n| async move {
+            let thumb = CoverKey::variant(series, n, CoverSize::Thumb, CoverFormat::Jpeg);
+            (n, state.cover_store.get(thumb).await)
+        })
+        .buffered(MONTAGE_CONCURRENCY)
+        .collect()
+        .await;
+    let complete = complete && covers.iter().all(|(_, image)| image.is_some());
+
+    let montage = tokio::task::spawn_blocking(move || {
+        encode(&draw_montage(series, &title, covers), CoverFormat::Jpeg).map_err(|e| e.to_string())
+    }).await;
+    match montage {
+        Ok(Ok(bytes)) if complete => { PrResultBuilder::cached_image(bytes, etag) }
+        Ok(Ok(bytes)) => { PrResultBuilder::placeholder_image(bytes) }
+        Ok(Err(e)) => { Err(DrawingMontage(e, number)) }
+        Err(e) => { Err(DrawingMontage(e.to_string(), number)) }
+    }
+}
@@ -1,51 +1,71 @@
 use std::collections::HashMap;
 use askama::Template;
-use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::*;
 use crate::banner_info::BannerInfo;
-use crate::entities::{Book, Cycle, Summary};
+use crate::entities::{Book, CoverSize, Cycle, CycleStats, Rating, Series, Summary};
 use crate::errors::{Error, PrResult, PrResultBuilder};
+use crate::logic::is_spoiler;
 use crate::{CookieManager, PerryState};
 use crate::url::Urls;
 
-pub async fn index_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+pub async fn index_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>, series: Series)
     -> PrResult
 {
     // Cycles
     let mut cycles: Vec<HtmlTemplate> = Vec::new();
-    match state.db.fetch_cycles().await {
+    match state.db.fetch_cycles(series).await {
         Ok(all_cycles) => {
             let cycles_count = all_cycles.len() as i32;
+            let mut stats: HashMap<i32, CycleStats> = state.db.fetch_cycle_stats(series).await
+                .into_iter().map(|s| (s.number, s)).collect();
             for cycle in all_cycles {
-                cycles.push(HtmlTemplate::new(cycle, cycles_count).await);
+                let cycle_stats = stats.remove(&cycle.number).unwrap_or_default();
+                cycles.push(HtmlTemplate::new(cycle, cycles_count, cycle_stats).await);
             }
 
             // Summaries
-            let rs: Vec<Summary> = state.db.fetch_most_recent_summaries().await;
+            let rs: Vec<Summary> = state.db.fetch_most_recent_summaries(series).await;
             let numbers: Vec<u32> = rs.iter().map(|s| s.number as u32).collect();
-            let cover_urls: Vec<String> = state.cover_finder.find_cover_urls(numbers).await
+            let cover_urls: Vec<String> = state.cover_finder.find_cover_urls(series, numbers, CoverSize::Thumb).await
                 .iter().map(|url| {
                 match url {
                     None => { "".to_string() }
                     Some(s) => { s.clone() }
                 }
             }).collect();
+            // Spoiler-safe mode only knows about the main series
+            let read_up_to = cookie_manager.find_read_up_to().await.filter(|_| series == Series::Pr);
             let mut recent_summaries: Vec<TemplateRecentSummary> = Vec::new();
             for (i, s) in rs.iter().enumerate() {
-                recent_summaries.push(
-                    TemplateRecentSummary::new(s.clone(), cover_urls[i].clone()).await);
+                let mut recent = TemplateRecentSummary::new(s.clone(), cover_urls[i].clone()).await;
+                recent.spoiler = is_spoiler(read_up_to, s.number);
+                recent_summaries.push(recent);
             }
-            let summary_count = state.db.fetch_summary_count().await;
-            let book_count = state.db.fetch_book_count().await;
+            // Books of the past week and the next four
+            let today = Local::now().date_naive();
+            let published_this_week = state.db.find_books_published_between(series,
+                today - Days::new(6), today).await
+                .into_iter().map(TemplatePublished::from).collect();
+            let upcoming = state.db.find_books_published_between(series,
+                today + Days::new(1), today + Days::new(28)).await
+                .into_iter().map(TemplatePublished::from).collect();
+            let summary_count = state.db.fetch_summary_count(series).await;
+            let book_count = state.db.fetch_book_count(series).await;
             let user = cookie_manager.find_user(state.db.clone()).await;
             let template = TemplateCycles {
                 summary_count,
-                percentage: (summary_count as u32 * 100 / book_count as u32) as u8,
+                percentage: (summary_count as u32 * 100 / (book_count as u32).max(1)) as u8,
                 recent_summaries,
+                published_this_week,
+                upcoming,
                 cycles,
                 banner_info: BannerInfo::new(user).await,
+                read_up_to: read_up_to.map_or("".into(), |n| n.to_string()),
+                series,
+                all_series: Series::ALL.iter().map(|s| (s.name(), Urls::series(*s) + "/")).collect(),
             };
             // println!("Template: {result}");
 
@@ -58,16 +78,32 @@ pub async fn index_logic<T>(state: &PerryState, cookie_manager: impl CookieManag
     }
 }
 
-pub async fn api_cycles_logic(state: &PerryState, number: u32) -> PrResult {
-    match state.db.find_cycle(number).await {
+pub async fn api_cycles_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32)
+    -> PrResult
+{
+    let template_cycle = find_cycle_data(state, cookie_manager, series, number).await?;
+    PrResultBuilder::json(serde_json::to_string(&json!(template_cycle)).unwrap())
+}
+
+/// The books of a cycle, shared by the cycle page and its JSON API
+pub(crate) async fn find_cycle_data<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32)
+    -> Result<TemplateCycle, Error>
+{
+    let main_series = series == Series::Pr;
+    let read_up_to = cookie_manager.find_read_up_to().await.filter(|_| main_series);
+    match state.db.find_cycle(series, number).await {
         Ok(cycle) => {
             let mut books: Vec<TemplateBook> = Vec::new();
-            let db_books = state.db.find_books(number).await.unwrap_or(Vec::new());
-            let db_summaries = state.db.find_summaries(number).await.unwrap_or(Vec::new());
+            let db_books = state.db.find_books(series, number).await.unwrap_or(Vec::new());
+            let db_summaries = state.db.find_summaries(series, number).await.unwrap_or(Vec::new());
             let mut map: HashMap<i32, String> = HashMap::new();
             for summary in db_summaries {
                 map.insert(summary.number, summary.english_title);
             }
+            let mut ratings: HashMap<i32, Rating> = state.db.find_ratings(series, cycle.start, cycle.end).await
+                .into_iter().map(|r| (r.number, r)).collect();
             for book in db_books {
                 let number_string = if book.number == cycle.start {
                     format!("heft {}", book.number)
@@ -76,23 +112,32 @@ pub async fn api_cycles_logic(state: &PerryState, number: u32) -> PrResult {
                 };
                 let english_title = map.get(&book.number).unwrap_or(&"".to_string()).clone();
                 let book_number = book.number;
+                let published = to_pretty_date(book.published.map(|d| d.to_string()));
                 books.push(TemplateBook {
+                    published,
                     book,
                     english_title,
                     number_string,
-                    href: format!("/summaries/{book_number}"),
+                    href: Urls::summary(series, book_number),
+                    href_book: format!("{}/edit", Urls::book(series, book_number)),
+                    rating: ratings.remove(&book_number),
+                    spoiler: is_spoiler(read_up_to, book_number),
                 })
             }
 
             let german_title = cycle.german_title.clone();
-            let template_cycle = TemplateCycle {
+            let stats = state.db.fetch_cycle_stats(series).await.into_iter()
+                .find(|s| s.number == cycle.number)
+                .unwrap_or_default();
+            Ok(TemplateCycle {
+                percentage: stats.percentage(),
+                stats,
                 cycle,
                 books,
                 number,
                 german_title,
-                href_back: Urls::root(),
-            };
-            PrResultBuilder::json(serde_json::to_string(&json!(template_cycle)).unwrap())
+                href_back: Urls::series(series) + "/",
+            })
         }
         Err(e) => {
             Err(Error::FetchingCycle(format!("Couldn't fetch cycle {number}: {e}"), number))
@@ -134,17 +179,37 @@ pub fn to_pretty_date(date: Option<String>) -> String {
 
 pub struct TemplateRecentSummary {
     pub summary: Summary,
+    pub href: String,
     pub cover_url: String,
     pub pretty_date: String,
+    pub spoiler: bool,
 }
 
 impl TemplateRecentSummary {
     pub(crate) async fn new(summary: Summary, cover_url: String) -> Self {
         let pretty_date = to_pretty_date(summary.date.clone());
         Self {
+            href: Urls::summary(summary.series, summary.number),
             summary,
             cover_url,
             pretty_date,
+            spoiler: false,
+        }
+    }
+}
+
+pub struct TemplatePublished {
+    pub book: Book,
+    pub href: String,
+    pub pretty_date: String,
+}
+
+impl From<Book> for TemplatePublished {
+    fn from(book: Book) -> Self {
+        Self {
+            href: Urls::summary(book.series, book.number),
+            pretty_date: to_pretty_date(book.published.map(|d| d.to_string())),
+            book,
         }
     }
 }
@@ -156,35 +221,53 @@ pub struct TemplateCycles {
     pub percentage: u8,
     pub banner_info: BannerInfo,
     pub recent_summaries: Vec<TemplateRecentSummary>,
+    pub published_this_week: Vec<TemplatePublished>,
+    pub upcoming: Vec<TemplatePublished>,
     pub cycles: Vec<HtmlTemplate>,
+    /// Spoiler-safe mode, empty if not set
+    pub read_up_to: String,
+    pub series: Series,
+    /// Name and home page of each series
+    pub all_series: Vec<(&'static str, String)>,
 }
 
 #[derive(Deserialize, Serialize)]
-struct TemplateBook {
-    book: Book,
-    english_title: String,
-    number_string: String,
-    href: String,
+pub(crate) struct TemplateBook {
+    pub book: Book,
+    pub english_title: String,
+    pub number_string: String,
+    pub href: String,
+    pub href_book: String,
+    /// Publication date, empty if unknown
+    pub published: String,
+    pub rating: Option<Rating>,
+    pub spoiler: bool,
 }
 
 #[derive(Deserialize, Serialize)]
-struct TemplateCycle {
+pub(crate) struct TemplateCycle {
     pub cycle: Cycle,
     pub books: Vec<TemplateBook>,
     pub number: u32,
     pub german_title: String,
     pub href_back: String,
+    pub stats: CycleStats,
+    pub percentage: u8,
 }
 
 pub struct HtmlTemplate {
     pub cycle: Cycle,
     pub number_string: String,
     pub href: String,
+    pub stats: CycleStats,
+    pub percentage: u8,
+    pub last_date: String,
 }
 
 impl HtmlTemplate {
-    pub(crate) async fn new(cycle: Cycle, cycle_count: i32) -> Self {
+    pub(crate) async fn new(cycle: Cycle, cycle_count: i32, stats: CycleStats) -> Self {
         let number = cycle.number;
+        let series = cycle.series;
         let number_string = if cycle.number == cycle_count {
             format!("cycle {}", cycle.number)
         } else {
@@ -193,7 +276,10 @@ impl HtmlTemplate {
         Self {
             cycle,
             number_string,
-            href: Urls::cycles(number)
+            href: Urls::cycles(series, number),
+            percentage: stats.percentage(),
+            last_date: to_pretty_date(stats.last_date.clone()),
+            stats,
         }
     }
 }
@@ -206,21 +292,71 @@ pub struct CycleFormData {
     pub short_title: String,
     pub start: i32,
     pub end: i32,
+    #[serde(default)]
+    pub series: Series,
+    /// The range for which the admin reviewed the books changing cycle
+    #[serde(default)]
+    pub reviewed_start: Option<i32>,
+    #[serde(default)]
+    pub reviewed_end: Option<i32>,
 }
 
-pub async fn insert_cycle_logic(state: &PerryState, form_data: CycleFormData) -> PrResult {
-    let cycle = Cycle {
-        number: form_data.number,
-        german_title: form_data.german_title,
-        english_title: form_data.english_title,
-        short_title: form_data.short_title,
-        start: form_data.start,
-        end: form_data.end,
+impl CycleFormData {
+    /// The review only holds if the range wasn't changed on the confirmation screen
+    fn is_reviewed(&self) -> bool {
+        self.reviewed_start == Some(self.start) && self.reviewed_end == Some(self.end)
+    }
+}
+
+impl From<CycleFormData> for Cycle {
+    fn from(form_data: CycleFormData) -> Self {
+        Cycle {
+            series: form_data.series,
+            number: form_data.number,
+            german_title: form_data.german_title,
+            english_title: form_data.english_title,
+            short_title: form_data.short_title,
+            start: form_data.start,
+            end: form_data.end,
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "insert_cycle.html")]
+struct TemplateInsertCycle {
+    all_series: [Series; 3],
+    cycle: Cycle,
+    error: String,
+}
+
+pub async fn insert_cycle_form_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let template = TemplateInsertCycle {
+        all_series: Series::ALL,
+        cycle: Cycle::default(),
+        error: "".into(),
     };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+pub async fn insert_cycle_logic(state: &PerryState, form_data: CycleFormData) -> PrResult {
+    let number = form_data.number;
+    let cycle: Cycle = form_data.into();
+    if let Err(error) = validate_cycle(&state.db.fetch_cycles(cycle.series).await?, None, &cycle) {
+        warn!("Rejecting new cycle {number}: {error}");
+        let template = TemplateInsertCycle { all_series: Series::ALL, cycle, error };
+        return PrResultBuilder::html(template.render().unwrap());
+    }
 
     match state.db.insert_cycle(cycle).await {
         Ok(_) => {
-            info!("Successfully inserted cycle {}", form_data.number);
+            info!("Successfully inserted cycle {}", number);
             PrResultBuilder::redirect("/".to_string())
         }
         Err(e) => {
@@ -229,3 +365,504 @@ pub async fn insert_cycle_logic(state: &PerryState, form_data: CycleFormData) ->
         }
     }
 }
+
+/// Check a new cycle, or the new version of cycle `original`, against all the existing ones
+pub fn validate_cycle(cycles: &[Cycle], original: Option<i32>, cycle: &Cycle) -> Result<(), String> {
+    if cycle.number < 1 {
+        return Err("The cycle number must be positive".into());
+    }
+    if cycle.start < 1 || cycle.start > cycle.end {
+        return Err(format!("Invalid range {}-{}: the start must be positive and not after the end",
+            cycle.start, cycle.end));
+    }
+    let others = cycles.iter().filter(|c| Some(c.number) != original);
+    for other in others {
+        if other.number == cycle.number {
+            return Err(format!("Cycle {} already exists: {}", other.number, other.english_title));
+        }
+        if cycle.start <= other.end && other.start <= cycle.end {
+            return Err(format!("Books {}-{} overlap with cycle {} ({}, books {}-{})",
+                cycle.start.max(other.start), cycle.end.min(other.end),
+                other.number, other.english_title, other.start, other.end));
+        }
+    }
+    Ok(())
+}
+
+//
+// Editing and deleting cycles
+//
+
+/// A run of consecutive books that an edit moves from one cycle to another
+pub struct MembershipChange {
+    pub start: i32,
+    pub end: i32,
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+
+impl MembershipChange {
+    fn label(cycle: Option<i32>) -> String {
+        cycle.map_or("no cycle".into(), |n| format!("cycle {n}"))
+    }
+    pub fn old_label(&self) -> String { Self::label(self.from) }
+    pub fn new_label(&self) -> String { Self::label(self.to) }
+}
+
+fn owner(cycles: &[Cycle], book: i64) -> Option<i32> {
+    cycles.iter().find(|c| c.start as i64 <= book && book <= c.end as i64).map(|c| c.number)
+}
+
+/// Which books would belong to a different cycle if cycle `number` were replaced by `edited`
+/// (or deleted if `edited` is `None`). The owners only change where a cycle starts or ends, so
+/// only these boundaries are looked at, however large the ranges.
+pub fn membership_changes(cycles: &[Cycle], number: i32, edited: Option<&Cycle>) -> Vec<MembershipChange> {
+    // The edited cycle comes first so that it wins the books it now overlaps with
+    let after: Vec<Cycle> = edited.into_iter()
+        .chain(cycles.iter().filter(|c| c.number != number))
+        .cloned()
+        .collect();
+    let touched: Vec<&Cycle> = cycles.iter().filter(|c| c.number == number).chain(edited).collect();
+    let (Some(first), Some(last)) = (touched.iter().map(|c| c.start as i64).min(),
+            touched.iter().map(|c| c.end as i64).max()) else {
+        return Vec::new();
+    };
+
+    let mut boundaries: Vec<i64> = cycles.iter().chain(after.iter())
+        .flat_map(|c| [c.start as i64, c.end as i64 + 1])
+        .filter(|b| first < *b && *b <= last)
+        .chain([first, last + 1])
+        .collect();
+    boundaries.sort();
+    boundaries.dedup();
+
+    let mut result: Vec<MembershipChange> = Vec::new();
+    for range in boundaries.windows(2) {
+        let (start, end) = (range[0], range[1] - 1);
+        let (from, to) = (owner(cycles, start), owner(&after, start));
+        if from == to {
+            continue;
+        }
+        match result.last_mut() {
+            Some(c) if c.end as i64 == start - 1 && c.from == from && c.to == to => {
+                c.end = end as i32;
+            }
+            _ => {
+                result.push(MembershipChange { start: start as i32, end: end as i32, from, to });
+            }
+        }
+    }
+    result
+}
+
+#[derive(Template)]
+#[template(path = "edit_cycle.html")]
+struct TemplateEditCycle {
+    /// The number of the cycle before this edit
+    original: i32,
+    /// Where the form posts to, without the trailing "/edit" or "/delete"
+    base_url: String,
+    cycle: Cycle,
+    changes: Vec<MembershipChange>,
+    /// The books that deleting the cycle would leave without one, before it's confirmed
+    deletion: Vec<MembershipChange>,
+    error: String,
+}
+
+pub(crate) async fn is_admin<T>(state: &PerryState, cookie_manager: impl CookieManager<T>) -> bool {
+    cookie_manager.find_user(state.db.clone()).await.is_some_and(|u| u.is_admin())
+}
+
+pub async fn edit_cycle_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let cycle = state.db.find_cycle(series, number).await?;
+    let template = TemplateEditCycle {
+        original: cycle.number,
+        base_url: Urls::cycles(series, cycle.number),
+        cycle,
+        changes: Vec::new(),
+        deletion: Vec::new(),
+        error: "".into(),
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+/// Save the cycle, unless some books would change cycle and the admin hasn't confirmed it yet,
+/// in which case the form is displayed again with the list of these books
+pub async fn post_edit_cycle_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32, mut form_data: CycleFormData)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let confirmed = form_data.is_reviewed();
+    form_data.series = series;
+    let cycle: Cycle = form_data.into();
+    let cycles = state.db.fetch_cycles(series).await?;
+    let base_url = Urls::cycles(series, number as i32);
+    if let Err(error) = validate_cycle(&cycles, Some(number as i32), &cycle) {
+        warn!("Rejecting edit of cycle {number}: {error}");
+        let template = TemplateEditCycle {
+            original: number as i32,
+            base_url,
+            cycle,
+            changes: Vec::new(),
+            deletion: Vec::new(),
+            error,
+        };
+        return PrResultBuilder::html(template.render().unwrap());
+    }
+    let changes = membership_changes(&cycles, number as i32, Some(&cycle));
+    if ! changes.is_empty() && ! confirmed {
+        let template = TemplateEditCycle {
+            original: number as i32,
+            base_url,
+            cycle,
+            changes,
+            deletion: Vec::new(),
+            error: "".into(),
+        };
+        return PrResultBuilder::html(template.render().unwrap());
+    }
+
+    for change in &changes {
+        info!("Books {}-{} moving from {} to {}", change.start, change.end,
+            change.old_label(), change.new_label());
+    }
+    let new_number = cycle.number;
+    state.db.update_cycle(number as i32, cycle).await?;
+    PrResultBuilder::redirect(Urls::cycles(series, new_number))
+}
+
+/// Posted by the delete button of the edit form
+#[derive(Deserialize)]
+pub struct CycleDeleteFormData {
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+/// Delete the cycle once the admin has seen which books it leaves without a cycle
+pub async fn delete_cycle_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, number: u32, form_data: CycleDeleteFormData)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let cycles = state.db.fetch_cycles(series).await?;
+    let deletion = membership_changes(&cycles, number as i32, None);
+    if ! deletion.is_empty() && ! form_data.confirmed {
+        let cycle = state.db.find_cycle(series, number).await?;
+        let template = TemplateEditCycle {
+            original: cycle.number,
+            base_url: Urls::cycles(series, cycle.number),
+            cycle,
+            changes: Vec::new(),
+            deletion,
+            error: "".into(),
+        };
+        return PrResultBuilder::html(template.render().unwrap());
+    }
+
+    for change in &deletion {
+        info!("Books {}-{} no longer part of {}", change.start, change.end, change.old_label());
+    }
+    state.db.delete_cycle(series, number as i32).await?;
+    PrResultBuilder::root()
+}
+
+//
+// Coverage report
+//
+
+/// A range of book numbers that no cycle covers
+pub struct Gap {
+    pub start: i32,
+    pub end: i32,
+}
+
+/// The ranges between 1 and `last` that aren't part of any cycle
+pub fn find_gaps(cycles: &[Cycle], last: i32) -> Vec<Gap> {
+    let mut sorted: Vec<&Cycle> = cycles.iter().collect();
+    sorted.sort_by_key(|c| c.start);
+    let mut result = Vec::new();
+    let mut next = 1;
+    for cycle in sorted {
+        if cycle.start > next {
+            result.push(Gap { start: next, end: cycle.start - 1 });
+        }
+        next = next.max(cycle.end.saturating_add(1));
+    }
+    if last >= next {
+        result.push(Gap { start: next, end: last });
+    }
+    result
+}
+
+#[derive(Template)]
+#[template(path = "cycles_coverage.html")]
+struct TemplateCoverage {
+    series: Series,
+    /// "/neo" for the URL's of that series
+    prefix: String,
+    gaps: Vec<Gap>,
+    /// Pairs of existing cycles that claim the same books
+    overlaps: Vec<(Cycle, Cycle)>,
+    orphans: Vec<Book>,
+}
+
+pub async fn cycles_coverage_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    let (cycles, orphans) = tokio::join!(
+        state.db.fetch_cycles(series),
+        state.db.find_books_outside_cycles(series),
+    );
+    let cycles = cycles?;
+    let last = cycles.iter().map(|c| c.end)
+        .chain(orphans.iter().map(|b| b.number))
+        .max().unwrap_or(0);
+    let mut overlaps = Vec::new();
+    for (i, a) in cycles.iter().enumerate() {
+        for b in &cycles[i + 1..] {
+            if a.start <= b.end && b.start <= a.end {
+                overlaps.push((a.clone(), b.clone()));
+            }
+        }
+    }
+    let template = TemplateCoverage {
+        series,
+        prefix: Urls::series(series),
+        gaps: find_gaps(&cycles, last),
+        overlaps,
+        orphans,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+//
+// Importing cycles from PerryPedia
+//
+
+/// A cycle listed by PerryPedia that is missing here or differs from ours
+pub struct CycleProposal {
+    /// PerryPedia's version, with our English title if we have one
+    pub cycle: Cycle,
+    pub existing: Option<Cycle>,
+    /// What would change, e.g. "books 1-49 → 1-50"
+    pub differences: Vec<String>,
+    /// Why it couldn't be applied as is
+    pub error: String,
+}
+
+/// Compare the cycles found on PerryPedia with the existing ones
+pub fn propose_cycles(cycles: &[Cycle], imported: Vec<Cycle>) -> Vec<CycleProposal> {
+    let mut result = Vec::new();
+    for mut cycle in imported {
+        let existing = cycles.iter().find(|c| c.number == cycle.number).cloned();
+        let mut differences = Vec::new();
+        match &existing {
+            Some(e) => {
+                cycle.english_title = e.english_title.clone();
+                if e.german_title != cycle.german_title {
+                    differences.push(format!("German title \"{}\" → \"{}\"", e.german_title, cycle.german_title));
+                }
+                if e.short_title != cycle.short_title {
+                    differences.push(format!("short title \"{}\" → \"{}\"", e.short_title, cycle.short_title));
+                }
+                if e.start != cycle.start || e.end != cycle.end {
+                    differences.push(format!("books {}-{} → {}-{}", e.start, e.end, cycle.start, cycle.end));
+                }
+                if differences.is_empty() {
+                    continue;
+                }
+            }
+            None => {
+                differences.push(format!("new cycle, books {}-{}", cycle.start, cycle.end));
+            }
+        }
+        let error = validate_cycle(cycles, existing.as_ref().map(|c| c.number), &cycle)
+            .err().unwrap_or_default();
+        result.push(CycleProposal { cycle, existing, differences, error });
+    }
+    result
+}
+
+#[derive(Template)]
+#[template(path = "cycles_import.html")]
+struct TemplateCyclesImport {
+    series: Series,
+    /// "/neo" for the URL's of that series
+    prefix: String,
+    /// How many cycles were found on PerryPedia
+    found: usize,
+    proposals: Vec<CycleProposal>,
+    error: String,
+}
+
+async fn render_cycles_import(state: &PerryState, series: Series, error: String) -> PrResult {
+    let imported = state.cycle_finder.find_cycles(series).await;
+    let found = imported.len();
+    let template = TemplateCyclesImport {
+        series,
+        prefix: Urls::series(series),
+        found,
+        proposals: propose_cycles(&state.db.fetch_cycles(series).await?, imported),
+        error,
+    };
+    PrResultBuilder::html(template.render().unwrap())
+}
+
+/// Review the cycles that differ between PerryPedia and the database
+pub async fn cycles_import_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+    render_cycles_import(state, series, "".into()).await
+}
+
+/// Apply one of the proposals of the review screen
+pub async fn apply_cycle_import_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, mut form_data: CycleFormData)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+
+    form_data.series = series;
+    let cycle: Cycle = form_data.into();
+    let number = cycle.number;
+    let cycles = state.db.fetch_cycles(series).await?;
+    let exists = cycles.iter().any(|c| c.number == number);
+    if let Err(error) = validate_cycle(&cycles, exists.then_some(number), &cycle) {
+        warn!("Rejecting imported cycle {number}: {error}");
+        return render_cycles_import(state, series, format!("Cycle {number}: {error}")).await;
+    }
+
+    if exists {
+        for change in membership_changes(&cycles, number, Some(&cycle)) {
+            info!("Books {}-{} moving from {} to {}", change.start, change.end,
+                change.old_label(), change.new_label());
+        }
+        state.db.update_cycle(number, cycle).await?;
+    } else {
+        state.db.insert_cycle(cycle).await?;
+    }
+    info!("Imported cycle {series} {number} from PerryPedia");
+    PrResultBuilder::redirect(format!("{}/cycles/import", Urls::series(series)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::{Cycle, Series};
+    use crate::pages::cycles::{find_gaps, membership_changes, propose_cycles, validate_cycle, CycleFormData};
+    use crate::perrypedia::PerryPedia;
+
+    #[test]
+    fn review_covers_the_submitted_range() {
+        let form = |reviewed_start: Option<i32>, reviewed_end: Option<i32>| CycleFormData {
+            number: 2,
+            german_title: "".into(),
+            english_title: "".into(),
+            short_title: "".into(),
+            start: 50,
+            end: 99,
+            series: Series::Pr,
+            reviewed_start,
+            reviewed_end,
+        };
+        assert!(form(Some(50), Some(99)).is_reviewed());
+        // The range was edited on the confirmation screen
+        assert!(! form(Some(50), Some(120)).is_reviewed());
+        assert!(! form(None, None).is_reviewed());
+    }
+
+    #[test]
+    fn propose_imported_cycles() {
+        let html = include_str!("../../tests/fixtures/perrypedia/zyklen.html");
+        let existing = vec![
+            Cycle::builder().number(1).german_title("Die Dritte Macht".into())
+                .english_title("The Third Power".into()).short_title("Dritte Macht".into())
+                .start(1).end(49).build(),
+            Cycle::builder().number(2).german_title("Atlan und Arkon".into())
+                .english_title("Atlan and Arkon".into()).short_title("Atlan und Arkon".into())
+                .start(50).end(98).build(),
+        ];
+        let proposals = propose_cycles(&existing, PerryPedia::parse_cycles(html, Series::Pr));
+
+        assert_eq!(proposals.len(), 2);
+        assert_eq!(proposals[0].cycle.english_title, "Atlan and Arkon");
+        assert_eq!(proposals[0].differences, vec!["books 50-98 → 50-99".to_string()]);
+        assert!(proposals[0].error.is_empty());
+        assert!(proposals[1].existing.is_none());
+        assert_eq!((proposals[1].cycle.start, proposals[1].cycle.end), (100, 149));
+    }
+
+    #[test]
+    fn changes_from_boundaries() {
+        let cycles = vec![cycle(1, 1, 49), cycle(2, 50, 99), cycle(3, 100, 149)];
+        let summary = |edited: Option<&Cycle>| membership_changes(&cycles, 2, edited).iter()
+            .map(|c| (c.start, c.end, c.from, c.to))
+            .collect::<Vec<_>>();
+
+        assert_eq!(summary(Some(&cycle(2, 45, 99))), vec![(45, 49, Some(1), Some(2))]);
+        assert_eq!(summary(Some(&cycle(2, 50, 120))), vec![(100, 120, Some(3), Some(2))]);
+        assert_eq!(summary(Some(&cycle(2, 60, 99))), vec![(50, 59, Some(2), None)]);
+        assert_eq!(summary(None), vec![(50, 99, Some(2), None)]);
+        // Instant, however many books the range covers
+        assert_eq!(summary(Some(&cycle(2, 50, i32::MAX))),
+            vec![(100, 149, Some(3), Some(2)), (150, i32::MAX, None, Some(2))]);
+    }
+
+    fn cycle(number: i32, start: i32, end: i32) -> Cycle {
+        Cycle::builder().number(number).german_title("".into()).english_title(format!("Cycle {number}"))
+            .short_title("".into()).start(start).end(end).build()
+    }
+
+    #[test]
+    fn validate() {
+        let cycles = vec![cycle(1, 1, 49), cycle(2, 50, 99)];
+        assert!(validate_cycle(&cycles, None, &cycle(3, 100, 149)).is_ok());
+        assert!(validate_cycle(&cycles, Some(2), &cycle(2, 50, 120)).is_ok());
+        // Renumbering a cycle
+        assert!(validate_cycle(&cycles, Some(2), &cycle(3, 50, 99)).is_ok());
+
+        assert!(validate_cycle(&cycles, None, &cycle(0, 100, 149)).is_err());
+        assert!(validate_cycle(&cycles, None, &cycle(3, 0, 149)).is_err());
+        assert!(validate_cycle(&cycles, None, &cycle(3, 150, 149)).is_err());
+        assert_eq!(validate_cycle(&cycles, None, &cycle(2, 100, 149)),
+            Err("Cycle 2 already exists: Cycle 2".into()));
+        assert_eq!(validate_cycle(&cycles, Some(2), &cycle(2, 40, 99)),
+            Err("Books 40-49 overlap with cycle 1 (Cycle 1, books 1-49)".into()));
+    }
+
+    #[test]
+    fn gaps() {
+        let summary = |cycles: &[Cycle], last| find_gaps(cycles, last).iter()
+            .map(|g| (g.start, g.end))
+            .collect::<Vec<_>>();
+        assert_eq!(summary(&[], 10), vec![(1, 10)]);
+        assert_eq!(summary(&[cycle(2, 50, 99), cycle(1, 1, 49)], 99), vec![]);
+        assert_eq!(summary(&[cycle(3, 120, 149), cycle(1, 5, 49), cycle(2, 40, 99)], 160),
+            vec![(1, 4), (100, 119), (150, 160)]);
+        assert_eq!(summary(&[cycle(1, 1, i32::MAX)], 100), vec![]);
+    }
+}
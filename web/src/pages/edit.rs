@@ -2,12 +2,14 @@ use askama::Template;
 use serde::Deserialize;
 use tracing::{error, info};
 
-use crate::entities::{Book, Cycle, Summary};
-use crate::errors::{PrResult, PrResultBuilder};
+use crate::entities::{Book, CoverSize, Cycle, Series, Summary};
+use crate::pages::cycles::is_admin;
+use crate::url::Urls;
+use crate::errors::{Error, PrResult, PrResultBuilder};
 use crate::{CookieManager, PerryState};
 
 pub async fn edit_summary_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
-        book_number: u32)
+        series: Series, book_number: u32)
     -> PrResult
 {
     let user = cookie_manager.find_user(state.db.clone()).await;
@@ -16,12 +18,12 @@ pub async fn edit_summary_logic<T>(state: &PerryState, cookie_manager: impl Cook
     } else {
         "Anonymous".to_string()
     };
-    info!("{editor} editing summary {book_number}");
+    info!("{editor} editing summary {series} {book_number}");
     match tokio::join!(
-            state.db.find_summary(book_number),
-            state.db.find_cycle_by_book(book_number),
-            state.db.find_book(book_number),
-            state.cover_finder.find_cover_url(book_number))
+            state.db.find_summary(series, book_number),
+            state.db.find_cycle_by_book(series, book_number),
+            state.db.find_book(series, book_number),
+            state.cover_finder.find_cover_url(series, book_number, CoverSize::Medium))
     {
         (Some(summary), Some(cycle), Some(book), cover_url) => {
             let template = TemplateEdit {
@@ -29,7 +31,7 @@ pub async fn edit_summary_logic<T>(state: &PerryState, cookie_manager: impl Cook
                 summary,
                 cycle,
                 cover_url: cover_url.unwrap_or("".to_string()),
-                cancel_url: format!("/summaries/{}", book_number),
+                cancel_url: Urls::summary(series, book_number as i32),
             };
             PrResultBuilder::html(template.render().unwrap())
         }
@@ -46,14 +48,23 @@ pub async fn edit_summary_logic<T>(state: &PerryState, cookie_manager: impl Cook
             }
             template.book = if let Some(b) = book { b } else {
                 Book {
+                    series,
                     number: book_number as i32,
                     ..Default::default()
                 }
             };
+            if template.book.title.is_empty() {
+                // New Heft: prefill what PerryPedia knows about it
+                if let Some(metadata) = state.metadata_finder.find_book_metadata(series, book_number).await {
+                    template.book.title = metadata.title;
+                    template.book.author = metadata.author;
+                    template.book.published = template.book.published.or(metadata.published);
+                }
+            }
             template.cycle = cycle;
             template.book.number = book_number as i32;
             template.cover_url = cover_url.unwrap_or("".to_string());
-            template.cancel_url = format!("/summaries/{}", book_number);
+            template.cancel_url = Urls::summary(series, book_number as i32);
             PrResultBuilder::html(template.render().unwrap())
         }
         _ => {
@@ -63,6 +74,32 @@ pub async fn edit_summary_logic<T>(state: &PerryState, cookie_manager: impl Cook
     }
 }
 
+/// Admin action: replace the title, author and publication date of a book with PerryPedia's
+pub async fn refresh_metadata_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    if ! is_admin(state, cookie_manager).await {
+        return PrResultBuilder::root();
+    }
+    let metadata = state.metadata_finder.find_book_metadata(series, book_number).await
+        .ok_or(Error::FetchingMetadata("no Quelle page found".into(), book_number))?;
+    let book = state.db.find_book(series, book_number).await.unwrap_or(Book {
+        series,
+        number: book_number as i32,
+        ..Default::default()
+    });
+    info!("Refreshing the metadata of {series} {book_number}: {metadata:?}");
+    state.db.update_or_insert_book(Book {
+        title: metadata.title,
+        author: metadata.author,
+        published: metadata.published.or(book.published),
+        ..book
+    }).await?;
+
+    PrResultBuilder::redirect(Urls::summary(series, book_number as i32))
+}
+
 #[derive(Default, Template)]
 #[template(path = "edit_summary.html")]
 struct TemplateEdit {
@@ -75,6 +112,9 @@ struct TemplateEdit {
 
 #[derive(Deserialize)]
 pub struct FormData {
+    /// The main series if the form doesn't say
+    #[serde(default)]
+    pub series: Series,
     pub number: u16,
     pub german_title: String,
     pub english_title: String,
@@ -84,5 +124,8 @@ pub struct FormData {
     pub date: Option<String>,
     pub _time: Option<String>,
     pub author_name: String,
+    /// Publication date of the book, yyyy-mm-dd
+    #[serde(default)]
+    pub published: Option<String>,
 }
 
@@ -3,3 +3,8 @@ pub mod summaries;
 pub mod edit;
 pub mod pending;
 pub mod cycle;
+pub mod comments;
+pub mod reading;
+pub mod characters;
+pub mod books;
+pub mod covers;
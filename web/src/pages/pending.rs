@@ -13,6 +13,7 @@ pub async fn pending_logic<T>(state: &PerryState, cookie_manager: impl CookieMan
                 number: s.number,
                 english_title: s.english_title.clone(),
                 date_summary: s.date_summary.clone(),
+                published: s.published.clone().unwrap_or_default(),
             }
         }).collect();
 
@@ -37,5 +38,6 @@ struct PendingSummaryTemplate {
     number: i32,
     english_title: String,
     date_summary: String,
+    published: String,
 }
 
@@ -0,0 +1,168 @@
+use askama::Template;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+use crate::banner_info::BannerInfo;
+use crate::entities::{CycleProgress, Reading, ReadingStatus, Series};
+use crate::errors::{PrResult, PrResultBuilder};
+use crate::url::Urls;
+use crate::{CookieManager, PerryState};
+
+#[derive(Deserialize)]
+pub struct ReadingFormData {
+    pub status: ReadingStatus,
+    /// Empty if the user didn't rate the book
+    pub rating: Option<String>,
+}
+
+/// The reading status of the logged in user for that book, `null` if nobody is logged in
+pub async fn api_reading_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    let reading = match cookie_manager.find_user(state.db.clone()).await {
+        Some(user) => {
+            Some(state.db.find_reading(&user.login, series, book_number).await.unwrap_or(Reading {
+                login: user.login,
+                series,
+                number: book_number as i32,
+                ..Default::default()
+            }))
+        }
+        None => { None }
+    };
+    PrResultBuilder::json(serde_json::to_string(&json!(reading)).unwrap())
+}
+
+pub async fn post_reading_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32, form: ReadingFormData)
+    -> PrResult
+{
+    // The book numbers come straight from the URL
+    if state.db.find_book(series, book_number).await.is_none() {
+        warn!("Can't update the reading status of {series} {book_number}: unknown book");
+        return PrResultBuilder::not_found();
+    }
+
+    if let Some(user) = cookie_manager.find_user(state.db.clone()).await {
+        let rating = form.rating
+            .and_then(|r| r.parse::<i32>().ok())
+            .filter(|r| (1..=5).contains(r));
+        state.db.update_reading(Reading {
+            login: user.login,
+            series,
+            number: book_number as i32,
+            status: form.status,
+            rating,
+        }).await?;
+    } else {
+        warn!("Can't update the reading status of {series} {book_number}: no user logged in");
+    }
+
+    PrResultBuilder::redirect(Urls::summary(series, book_number as i32))
+}
+
+struct TemplateProgress {
+    progress: CycleProgress,
+    percentage: u8,
+    href: String,
+}
+
+#[derive(Template)]
+#[template(path = "reading.html")]
+struct TemplateReading {
+    banner_info: BannerInfo,
+    series: Series,
+    cycles: Vec<TemplateProgress>,
+    read: i64,
+    books: i64,
+}
+
+/// "My reading progress": how much of each cycle of that series the logged in user has read
+pub async fn reading_progress_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series)
+    -> PrResult
+{
+    match cookie_manager.find_user(state.db.clone()).await {
+        Some(user) => {
+            let progress = state.db.fetch_reading_progress(&user.login, series).await;
+            let read = progress.iter().map(|p| p.read).sum();
+            let books = progress.iter().map(|p| p.books).sum();
+            let cycles = progress.into_iter().map(|p| {
+                let percentage = if p.books == 0 { 0 } else { (p.read * 100 / p.books) as u8 };
+                let href = Urls::cycles(series, p.number);
+                TemplateProgress { progress: p, percentage, href }
+            }).collect();
+            let template = TemplateReading {
+                banner_info: BannerInfo::new(Some(user)).await,
+                series,
+                cycles,
+                read,
+                books,
+            };
+            PrResultBuilder::html(template.render().unwrap())
+        }
+        None => {
+            PrResultBuilder::root()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use async_trait::async_trait;
+    use crate::db::Db;
+    use crate::entities::{Book, Reading, ReadingStatus, Series, User};
+    use crate::errors::{DbResult, OkContent};
+    use crate::pages::reading::{post_reading_logic, ReadingFormData};
+    use crate::test::tests::create_state;
+    use crate::CookieManager;
+
+    /// Only knows book 12, and remembers the readings it's given
+    #[derive(Default)]
+    struct DbReading {
+        readings: Arc<Mutex<Vec<Reading>>>,
+    }
+
+    #[async_trait]
+    impl Db for DbReading {
+        async fn find_book(&self, series: Series, book_number: u32) -> Option<Book> {
+            (book_number == 12).then(|| Book { series, number: 12, ..Default::default() })
+        }
+
+        async fn update_reading(&self, reading: Reading) -> DbResult<()> {
+            self.readings.lock().unwrap().push(reading);
+            Ok(())
+        }
+    }
+
+    struct ReaderCookies;
+
+    #[async_trait]
+    impl CookieManager<()> for ReaderCookies {
+        async fn find_user(&self, _db: Arc<Box<dyn Db>>) -> Option<User> {
+            Some(User::builder().login("reader".into()).password(Vec::new()).name("Reader".into())
+                .level(1).email("".into()).build())
+        }
+        async fn create_auth_token_cookie(&self, _auth_token: String, _days: u16) {}
+        async fn find_read_up_to(&self) -> Option<u32> { None }
+        async fn create_read_up_to_cookie(&self, _read_up_to: Option<u32>) {}
+    }
+
+    #[tokio::test]
+    async fn unknown_books_are_refused() {
+        let db = DbReading::default();
+        let readings = db.readings.clone();
+        let state = create_state(Box::new(db)).await;
+        let form = || ReadingFormData { status: ReadingStatus::Read, rating: Some("4".into()) };
+
+        let result = post_reading_logic(&state, ReaderCookies, Series::Pr, 123456, form()).await;
+        assert!(matches!(result, Ok(OkContent::NotFound)));
+        assert!(readings.lock().unwrap().is_empty());
+
+        let result = post_reading_logic(&state, ReaderCookies, Series::Pr, 12, form()).await;
+        assert!(matches!(result, Ok(OkContent::Redirect(_))));
+        assert_eq!(readings.lock().unwrap().len(), 1);
+    }
+}
@@ -1,11 +1,14 @@
 use askama::Template;
+use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::error;
 use crate::banner_info::BannerInfo;
-use crate::entities::{Cycle, Summary};
+use crate::entities::{CoverSize, Cycle, Rating, Series, Summary};
 use crate::errors::{PrResult, PrResultBuilder};
-use crate::logic::save_summary_logic;
+use crate::logic::{is_spoiler, save_summary_logic};
+use crate::references::render_summary_text;
+use crate::pages::characters::TemplateEntity;
 use crate::pages::cycles::to_pretty_date;
 use crate::pages::edit::FormData;
 use crate::{CookieManager, PerryState};
@@ -14,18 +17,111 @@ use crate::url::Urls;
 /// This is used by the text field on the main page: if the user types a number
 /// and Submit, take them directly to that summary
 pub async fn summaries_post_logic(form_data: SingleSummaryData) -> PrResult {
-    PrResultBuilder::redirect(format!("/summaries/{}", form_data.number))
+    PrResultBuilder::redirect(Urls::summary(form_data.series, form_data.number as i32))
 }
 
-pub async fn summaries_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+pub async fn summaries_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
     -> PrResult
 {
-    let template = TemplateSummaries {
-        banner_info: BannerInfo::new(cookie_manager.find_user(state.db.clone()).await).await,
-    };
+    let banner_info = BannerInfo::new(cookie_manager.find_user(state.db.clone()).await).await;
+    let result = find_summary_data(state, cookie_manager, series, book_number).await;
+    let template = summary_page(banner_info, result, series, book_number as i32);
     PrResultBuilder::html(template.render().unwrap())
 }
 
+/// The title and description in the head end up in browser tabs, search results and link
+/// previews, where they can't be blurred: a spoiler only shows the German title there
+fn summary_page(banner_info: BannerInfo, result: TemplateSummary, series: Series, number: i32)
+    -> TemplateSummaries
+{
+    let (title, og_title, description) = if result.spoiler {
+        let title = format!("{} {}", result.summary.number, result.german_title);
+        let description = format!("English summary of {} {}", series.name(), result.summary.number);
+        (title.clone(), title, description)
+    } else {
+        (format!("{} {}", result.summary.number, result.summary.english_title),
+            format!("{} {} ({})", result.summary.number, result.summary.english_title,
+                result.german_title),
+            excerpt(&result.summary.summary, 200))
+    };
+    TemplateSummaries {
+        banner_info,
+        title,
+        og_title,
+        description,
+        result,
+        href_edit: format!("{}/edit", Urls::summary(series, number)),
+        href_metadata: format!("{}/metadata", Urls::summary(series, number)),
+        href_cover_upload: format!("{}/upload", Urls::cover(series, number)),
+        href_book: format!("{}/edit", Urls::book(series, number)),
+        href_previous: Urls::summary(series, (number - 1).max(1)),
+        href_next: Urls::summary(series, number + 1),
+        href_entities: Urls::summary_entities(series, number),
+        href_reading: format!("/api{}/reading", Urls::summary(series, number)),
+        href_comments: format!("/api{}/comments", Urls::summary(series, number)),
+    }
+}
+
+/// The beginning of a summary as plain text, for search engines and link previews. The entities
+/// are decoded since the template escapes the excerpt again.
+fn excerpt(html: &str, max_length: usize) -> String {
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = decode_entities(&tags.replace_all(html, " "));
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut result = String::new();
+    for word in words {
+        if result.chars().count() + word.chars().count() + 1 > max_length {
+            result.push_str("...");
+            break;
+        }
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+/// "&amp;", "&auml;", "&#8217;", "&#x2019;"... Unknown entities are left alone.
+fn decode_entities(text: &str) -> String {
+    let entities = Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z]+);").unwrap();
+    entities.replace_all(text, |cap: &Captures| {
+        let name = &cap[1];
+        let decoded = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+        } else if let Some(decimal) = name.strip_prefix('#') {
+            decimal.parse::<u32>().ok().and_then(char::from_u32)
+        } else {
+            match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "auml" => Some('ä'),
+                "ouml" => Some('ö'),
+                "uuml" => Some('ü'),
+                "Auml" => Some('Ä'),
+                "Ouml" => Some('Ö'),
+                "Uuml" => Some('Ü'),
+                "szlig" => Some('ß'),
+                "eacute" => Some('é'),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "lsquo" => Some('‘'),
+                "rsquo" => Some('’'),
+                "ldquo" => Some('“'),
+                "rdquo" => Some('”'),
+                "hellip" => Some('…'),
+                _ => None,
+            }
+        };
+        decoded.map_or(cap[0].to_string(), |c| c.to_string())
+    }).into_owned()
+}
+
 #[derive(Deserialize)]
 pub struct DisplaySummaryQueryParams {
     number: u32
@@ -35,17 +131,37 @@ pub async fn php_display_summary_logic(query: DisplaySummaryQueryParams) -> PrRe
     PrResultBuilder::redirect(format!("/summaries/{}", query.number))
 }
 
-pub async fn api_summaries_logic(state: &PerryState, book_number: u32) -> PrResult {
-    let template: TemplateSummary = {
+pub async fn api_summaries_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> PrResult
+{
+    let template = find_summary_data(state, cookie_manager, series, book_number).await;
+    PrResultBuilder::json(serde_json::to_string(&json!(template)).unwrap())
+}
+
+/// Everything displayed about a book, shared by the summary page and its JSON API
+async fn find_summary_data<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
+        series: Series, book_number: u32)
+    -> TemplateSummary
+{
+    let main_series = series == Series::Pr;
+    let mut template: TemplateSummary = {
         match tokio::join!(
-            state.db.find_summary(book_number),
-            state.db.find_cycle_by_book(book_number),
-            state.db.find_book(book_number),
-            state.cover_finder.find_cover_url(book_number),
-            state.db.find_cover(book_number),
+            state.db.find_summary(series, book_number),
+            state.db.find_cycle_by_book(series, book_number),
+            state.db.find_book(series, book_number),
+            state.cover_finder.find_cover_url(series, book_number, CoverSize::Medium),
+            state.db.find_cover(series, book_number),
         )
         {
-            (Some(summary), Some(cycle), Some(book), cover_url, cover) => {
+            (Some(mut summary), Some(cycle), Some(book), cover_url, cover) => {
+                summary.summary = render_summary_text(&state.db, series, &summary.summary, "").await;
+                let (mut ratings, entities) = tokio::join!(
+                    state.db.find_ratings(series, book_number as i32, book_number as i32),
+                    state.db.find_summary_entities(series, book_number),
+                );
+                let rating = ratings.pop();
+                let entities = entities.into_iter().map(TemplateEntity::from).collect();
                 let cycle_number = cycle.number;
                 let summary_date = summary.date.clone();
                 let perry_pedia_url = cover.map_or("".into(), |c| c.url.unwrap_or("".to_string()));
@@ -57,26 +173,35 @@ pub async fn api_summaries_logic(state: &PerryState, book_number: u32) -> PrResu
                     cycle,
                     book_author: book.author,
                     german_title: book.title,
+                    published: to_pretty_date(book.published.map(|d| d.to_string())),
                     hide_left: false,
-                    href_back: Urls::cycles(cycle_number),
+                    href_back: Urls::cycles(series, cycle_number),
                     href_edit: "".into(),
                     email_mailing_list: "".into(),
                     cover_url: cover_url.unwrap_or("".to_string()),
                     perry_pedia: perry_pedia_url,
+                    rating,
+                    entities,
+                    referenced_by: Vec::new(),
+                    spoiler: false,
+                    series,
                 }
             }
             (_, Some(cycle), book, cover_url, cover) => {
-                let (book_title, book_author) = match book {
-                    Some(book) => { (book.title, book.author) }
-                    None => { ("".into(), "".into()) }
+                let (book_title, book_author, published) = match book {
+                    Some(book) => { (book.title, book.author, book.published) }
+                    None => { ("".into(), "".into(), None) }
                 };
                 let mut result = TemplateSummary::default();
                 let perry_pedia_url = cover.map_or("".into(), |c| c.url.unwrap_or("".to_string()));
                 result.cycle = cycle;
                 result.german_title = book_title;
                 result.book_author = book_author;
+                result.published = to_pretty_date(published.map(|d| d.to_string()));
                 result.summary = Summary::default();
+                result.summary.series = series;
                 result.summary.number = book_number as i32;
+                result.series = series;
                 result.number = book_number;
                 result.cover_url = cover_url.unwrap_or("".to_string());
                 result.perry_pedia = perry_pedia_url;
@@ -89,7 +214,21 @@ pub async fn api_summaries_logic(state: &PerryState, book_number: u32) -> PrResu
         }
     };
 
-    PrResultBuilder::json(serde_json::to_string(&json!(template)).unwrap())
+    template.series = series;
+    // The reading position only applies to the main series
+    let read_up_to = if main_series { cookie_manager.find_read_up_to().await } else { None };
+    template.spoiler = is_spoiler(read_up_to, book_number as i32);
+    // The later summaries mentioning this book are spoilers too
+    template.referenced_by = state.db.find_backlinks(series, book_number).await.into_iter()
+        .map(|a| TemplateReference {
+            href: Urls::summary(a.series, a.number),
+            spoiler: is_spoiler(read_up_to, a.number),
+            number: a.number,
+            english_title: a.english_title,
+        })
+        .collect();
+
+    template
 }
 
 pub async fn post_summary_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>,
@@ -97,19 +236,39 @@ pub async fn post_summary_logic<T>(state: &PerryState, cookie_manager: impl Cook
     -> PrResult
 {
     let number = form.number as i32;
+    let series = form.series;
     let state2 = state.clone();
     if let Err(e) = save_summary_logic(state, cookie_manager.find_user(state2.db.clone()).await,
             form).await {
         error!("Error when saving the summary: {e}");
     };
 
-    PrResultBuilder::redirect(Urls::summary(number))
+    PrResultBuilder::redirect(Urls::summary(series, number))
 }
 
 #[derive(Template)]
 #[template(path = "summary.html")]
 struct TemplateSummaries {
     pub banner_info: BannerInfo,
+    result: TemplateSummary,
+    /// Used for the title tag
+    title: String,
+    og_title: String,
+    /// Plain text used for the description meta tags
+    description: String,
+    href_edit: String,
+    /// Admin action refreshing the book from PerryPedia
+    href_metadata: String,
+    href_cover_upload: String,
+    href_book: String,
+    href_previous: String,
+    href_next: String,
+    /// Tagging the entities of the summary
+    href_entities: String,
+    /// Reading status of the logged in user, read by the page's script and posted by its form
+    href_reading: String,
+    /// Comments, read by the page's script and posted by its form
+    href_comments: String,
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -126,11 +285,73 @@ struct TemplateSummary {
     email_mailing_list: String,
     book_author: String,
     german_title: String,
+    /// Publication date of the book, empty if unknown
+    published: String,
     pretty_date: String,
+    rating: Option<Rating>,
+    entities: Vec<TemplateEntity>,
+    /// The summaries that mention this book
+    referenced_by: Vec<TemplateReference>,
+    /// Past what the reader has read so far, blurred until revealed
+    spoiler: bool,
+    series: Series,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct TemplateReference {
+    number: i32,
+    english_title: String,
+    href: String,
+    spoiler: bool,
 }
 
 #[derive(Deserialize)]
 pub struct SingleSummaryData {
+    #[serde(default)]
+    series: Series,
     number: u32,
 }
 
+#[cfg(test)]
+mod tests {
+    use askama::Template;
+    use crate::banner_info::BannerInfo;
+    use crate::entities::Series;
+    use crate::pages::summaries::{excerpt, summary_page, TemplateSummary};
+
+    #[test]
+    fn excerpts_are_plain_text() {
+        assert_eq!(excerpt("<p>Perry&#8217;s ship &amp; <b>Atlan</b></p>", 200), "Perry’s ship & Atlan");
+        assert_eq!(excerpt("Gucky&nbsp;und Bully &auml;rgern &#x41;rkon &bogus; 1 &lt; 2", 200),
+            "Gucky und Bully ärgern Arkon &bogus; 1 < 2");
+        // Entities count as one character, not as their encoded length
+        assert_eq!(excerpt("&amp;&amp;&amp; &amp;&amp;&amp; abc", 7), "&&& &&&...");
+    }
+
+    #[test]
+    fn excerpts_count_characters() {
+        assert_eq!(excerpt("Größe über Maß", 14), "Größe über Maß");
+        assert_eq!(excerpt("Größe über Maß", 13), "Größe über...");
+    }
+
+    #[test]
+    fn spoilers_stay_out_of_the_head() {
+        let mut result = TemplateSummary::default();
+        result.summary.number = 2000;
+        result.summary.english_title = "Revealed Secret".into();
+        result.summary.summary = "<p>Rhodan meets the Cosmocrats</p>".into();
+        result.german_title = "ES".into();
+        result.spoiler = true;
+        let banner_info = BannerInfo {
+            username: "".into(),
+            is_admin: false,
+            is_editor: false,
+            admin_text: "".into(),
+        };
+        let html = summary_page(banner_info, result, Series::Pr, 2000).render().unwrap();
+        let head = &html[..html.find("</head>").unwrap()];
+        assert!(head.contains("2000 ES"));
+        assert!(!head.contains("Revealed Secret"));
+        assert!(!head.contains("Cosmocrats"));
+    }
+}
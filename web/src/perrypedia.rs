@@ -1,88 +1,421 @@
+use std::collections::HashMap;
+use std::process::exit;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use chrono::NaiveDate;
 use regex::Regex;
-use tokio::time::timeout;
-use tracing::{debug, info, warn};
+use reqwest::StatusCode;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+use crate::config::Config;
+use crate::constants::PRODUCTION_HOST;
+use crate::entities::{CoverSize, Cycle, Series};
+use crate::url::Urls;
 
-const HOST: &str = "https://www.perrypedia.de";
-pub const TIMEOUT_MS: u64 = 2_000;
+/// Before the first retry, doubled for each following one, plus up to as much again of jitter
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+/// How many wiki pages the cache keeps
+const CACHE_CAPACITY: usize = 1_000;
+/// Larger wiki pages are refused
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
 
 #[async_trait]
 pub trait CoverFinder: Send + Sync {
-    async fn find_cover_url(&self, _n: u32) -> Option<String> { None }
-    async fn find_cover_urls(&self, numbers: Vec<u32>) -> Vec<Option<String>> {
+    /// `size` is only a hint, finders that don't resize covers return the same URL for all sizes
+    async fn find_cover_url(&self, _series: Series, _n: u32, _size: CoverSize) -> Option<String> { None }
+    async fn find_cover_urls(&self, series: Series, numbers: Vec<u32>, size: CoverSize)
+        -> Vec<Option<String>>
+    {
         let mut result: Vec<Option<String>> = Vec::new();
         for n in numbers {
             // TODO: use join!()
-            result.push(self.find_cover_url(n).await);
+            result.push(self.find_cover_url(series, n, size).await);
         }
         result
     }
 }
 
+/// What PerryPedia knows about a book, read from its `Quelle:` page
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookMetadata {
+    pub title: String,
+    pub author: String,
+    pub published: Option<NaiveDate>,
+    /// German title of the cycle
+    pub cycle: Option<String>,
+}
+
+#[async_trait]
+pub trait BookMetadataFinder: Send + Sync {
+    async fn find_book_metadata(&self, _series: Series, _n: u32) -> Option<BookMetadata> { None }
+}
+
+#[async_trait]
+pub trait CycleFinder: Send + Sync {
+    /// The cycles PerryPedia lists for that series, without their English title
+    async fn find_cycles(&self, _series: Series) -> Vec<Cycle> { Vec::new() }
+}
+
 #[derive(Clone)]
 pub struct LocalImageProvider;
 
 #[async_trait]
 impl CoverFinder for LocalImageProvider {
-    async fn find_cover_url(&self, n: u32) -> Option<String> {
-        Some(format!("/covers/{n:04}"))
+    async fn find_cover_url(&self, series: Series, n: u32, size: CoverSize) -> Option<String> {
+        Some(Urls::cover_sized(series, n as i32, size))
     }
 }
 
+/// The client of PerryPedia, shared by the cover providers, the metadata and the cycles so that
+/// they all see the same circuit breaker and page cache
 #[derive(Clone)]
-pub struct PerryPedia;
+pub struct PerryPedia {
+    client: Arc<PerryPediaClient>,
+}
+
+struct PerryPediaClient {
+    http: reqwest::Client,
+    /// "https://www.perrypedia.de", without a trailing slash
+    base_url: String,
+    retries: u32,
+    breaker_failures: u32,
+    breaker_cooldown: Duration,
+    cache_duration: Duration,
+    breaker: Mutex<CircuitBreaker>,
+    /// URL -> (when it was read, page)
+    cache: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+/// Skips PerryPedia for a while after too many requests in a row failed, so that the pages
+/// that need it don't all wait for its timeout
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    /// Consecutive failures
+    failures: u32,
+    /// Once that's past, a single request is let through: it closes the breaker if it succeeds,
+    /// and opens it again if it fails
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// When the breaker is half open, the request allowed is the probe: the others wait for
+    /// another `cooldown`, by which time the probe has succeeded, failed or timed out
+    fn allows(&mut self, now: Instant, cooldown: Duration) -> bool {
+        match self.open_until {
+            None => { true }
+            Some(until) if now >= until => {
+                self.open_until = Some(now + cooldown);
+                true
+            }
+            Some(_) => { false }
+        }
+    }
+
+    fn success(&mut self) {
+        self.failures = 0;
+        self.open_until = None;
+    }
+
+    /// Whether that failure opened the breaker
+    fn failure(&mut self, threshold: u32, cooldown: Duration, now: Instant) -> bool {
+        self.failures += 1;
+        if self.failures >= threshold {
+            self.open_until = Some(now + cooldown);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Why a request didn't return the page
+enum RequestError {
+    /// Worth trying again: timeout, connection error, 5xx, 429
+    Transient(String),
+    /// PerryPedia answered, but not with the page (e.g. 404)
+    Status(StatusCode),
+    TooLarge,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DownloadError {
+    /// Larger than the limit, stopped as soon as that was clear
+    TooLarge,
+    Failed(String),
+}
 
 impl PerryPedia {
-    async fn read_url(url: String) -> Option<String> {
-        match reqwest::get(url.clone()).await {
-            Ok(response) => {
-                match response.text().await {
-                    Ok(text) => { Some(text) }
-                    Err(e) => {
-                        warn!("Couldn't extract text from {url}: {e}");
-                        None
+    /// PERRYPEDIA_URL, PERRYPEDIA_TIMEOUT_MS, PERRYPEDIA_RETRIES, PERRYPEDIA_BREAKER_FAILURES,
+    /// PERRYPEDIA_BREAKER_COOLDOWN_SECS and PERRYPEDIA_CACHE_SECS
+    pub fn new(config: &Config) -> Self {
+        let user_agent = format!("Perry/{} (https://{PRODUCTION_HOST})", env!("CARGO_PKG_VERSION"));
+        let http = match reqwest::Client::builder()
+            .user_agent(user_agent)
+            .timeout(Duration::from_millis(config.perrypedia_timeout_ms))
+            .build()
+        {
+            Ok(http) => { http }
+            Err(e) => {
+                error!("Couldn't create the PerryPedia client: {e}");
+                exit(1);
+            }
+        };
+        PerryPedia {
+            client: Arc::new(PerryPediaClient {
+                http,
+                base_url: config.perrypedia_url.trim_end_matches('/').into(),
+                retries: config.perrypedia_retries,
+                breaker_failures: config.perrypedia_breaker_failures.max(1),
+                breaker_cooldown: Duration::from_secs(config.perrypedia_breaker_cooldown_secs),
+                cache_duration: Duration::from_secs(config.perrypedia_cache_secs),
+                breaker: Mutex::new(CircuitBreaker::default()),
+                cache: Mutex::new(HashMap::new()),
+            })
+        }
+    }
+
+    /// A wiki page, from the cache if it was read recently
+    async fn read_url(&self, url: String) -> Option<String> {
+        let client = &self.client;
+        if let Some((read, page)) = client.cache.lock().unwrap().get(&url) {
+            if read.elapsed() < client.cache_duration {
+                debug!("PerryPedia cache hit for {url}");
+                return Some(page.clone());
+            }
+        }
+
+        match self.get(&url, MAX_PAGE_BYTES).await {
+            Ok(bytes) => {
+                let page = String::from_utf8_lossy(&bytes).to_string();
+                if ! client.cache_duration.is_zero() {
+                    let mut cache = client.cache.lock().unwrap();
+                    if cache.len() >= CACHE_CAPACITY {
+                        cache.retain(|_, (read, _)| read.elapsed() < client.cache_duration);
+                    }
+                    if cache.len() >= CACHE_CAPACITY {
+                        let oldest = cache.iter().min_by_key(|(_, (read, _))| *read).map(|(url, _)| url.clone());
+                        if let Some(oldest) = oldest {
+                            cache.remove(&oldest);
+                        }
                     }
+                    cache.insert(url, (Instant::now(), page.clone()));
                 }
+                Some(page)
             }
             Err(e) => {
-                warn!("Couldn't load {url}: {e}");
+                warn!("Couldn't load {url}: {e:?}");
                 None
             }
         }
     }
 
-    pub fn _summary_url(number: u32) -> String {
-        format!("https://www-perrypedia-de.translate.goog/wiki/Quelle:PR{number}\
-        ?_x_tr_sl=auto&_x_tr_tl=en&_x_tr_hl=en&_x_tr_pto=nui")
+    /// An image or any other file hosted by PerryPedia, never cached
+    pub async fn download(&self, url: &str, max_bytes: usize) -> Result<Vec<u8>, DownloadError> {
+        self.get(url, max_bytes).await
+    }
+
+    /// Through the circuit breaker, retrying the transient failures
+    async fn get(&self, url: &str, max_bytes: usize) -> Result<Vec<u8>, DownloadError> {
+        let client = &self.client;
+        if ! client.breaker.lock().unwrap().allows(Instant::now(), client.breaker_cooldown) {
+            return Err(DownloadError::Failed("PerryPedia is skipped after repeated failures".into()));
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.request(url, max_bytes).await {
+                Ok(bytes) => {
+                    client.breaker.lock().unwrap().success();
+                    return Ok(bytes);
+                }
+                Err(RequestError::Status(status)) => {
+                    // PerryPedia is up, that page just isn't there
+                    client.breaker.lock().unwrap().success();
+                    return Err(DownloadError::Failed(status.to_string()));
+                }
+                Err(RequestError::TooLarge) => {
+                    client.breaker.lock().unwrap().success();
+                    return Err(DownloadError::TooLarge);
+                }
+                Err(RequestError::Transient(e)) if attempt < client.retries => {
+                    let delay = RETRY_DELAY * 2u32.pow(attempt);
+                    let jitter = Duration::from_millis(fastrand::u64(0..=delay.as_millis() as u64));
+                    debug!("Retrying {url} in {} ms after: {e}", (delay + jitter).as_millis());
+                    sleep(delay + jitter).await;
+                    attempt += 1;
+                }
+                Err(RequestError::Transient(e)) => {
+                    let opened = client.breaker.lock().unwrap()
+                        .failure(client.breaker_failures, client.breaker_cooldown, Instant::now());
+                    if opened {
+                        warn!("PerryPedia failed {} times in a row, skipping it for {} seconds",
+                            client.breaker_failures, client.breaker_cooldown.as_secs());
+                    }
+                    return Err(DownloadError::Failed(e));
+                }
+            }
+        }
+    }
+
+    /// The body is read chunk by chunk, so that a huge file is abandoned early
+    async fn request(&self, url: &str, max_bytes: usize) -> Result<Vec<u8>, RequestError> {
+        let mut response = self.client.http.get(url).send().await
+            .map_err(|e| RequestError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            if response.content_length().is_some_and(|length| length > max_bytes as u64) {
+                return Err(RequestError::TooLarge);
+            }
+            let mut result = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(|e| RequestError::Transient(e.to_string()))? {
+                if result.len() + chunk.len() > max_bytes {
+                    return Err(RequestError::TooLarge);
+                }
+                result.extend_from_slice(&chunk);
+            }
+            Ok(result)
+        } else if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            Err(RequestError::Transient(status.to_string()))
+        } else {
+            Err(RequestError::Status(status))
+        }
+    }
+
+    pub fn quelle_url(&self, series: Series, number: u32) -> String {
+        format!("{}/wiki/Quelle:{}{number}", self.client.base_url, series.perry_pedia_prefix())
+    }
+
+    /// Parse the infobox of a `Quelle:` page, None if it doesn't have at least a title
+    pub fn parse_quelle(html: &str) -> Option<BookMetadata> {
+        let title = Self::infobox_value(html, "Titel")?;
+        if title.is_empty() {
+            return None;
+        }
+        Some(BookMetadata {
+            title,
+            author: Self::infobox_value(html, "Autor").unwrap_or_default(),
+            published: Self::infobox_value(html, "Erstmals erschienen")
+                .and_then(|d| Self::parse_german_date(&d)),
+            cycle: Self::infobox_value(html, "Zyklus").filter(|c| ! c.is_empty()),
+        })
+    }
+
+    /// The text of the cell following the `label:` cell, without its markup
+    fn infobox_value(html: &str, label: &str) -> Option<String> {
+        let re = Regex::new(&format!(r"(?s)<t[dh][^>]*>\s*{}:\s*</t[dh]>\s*<td[^>]*>(.*?)</td>",
+            regex::escape(label))).unwrap();
+        re.captures(html).map(|cap| Self::text(cap.get(1).unwrap().as_str()))
+    }
+
+    /// The text of an HTML fragment, with its entities decoded and its whitespace collapsed
+    fn text(html: &str) -> String {
+        let tags = Regex::new(r"<[^>]*>").unwrap();
+        let text = tags.replace_all(html, "");
+        let text = text.replace("&#160;", " ").replace("&nbsp;", " ")
+            .replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&");
+        text.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    /// "Freitag, 8. September 1961" or "8. September 1961"
+    fn parse_german_date(date: &str) -> Option<NaiveDate> {
+        const MONTHS: [&str; 12] = ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli",
+            "August", "September", "Oktober", "November", "Dezember"];
+        let re = Regex::new(r"(\d{1,2})\.\s*(\p{L}+)\s+(\d{4})").unwrap();
+        let cap = re.captures(date)?;
+        let day: u32 = cap[1].parse().ok()?;
+        let month = MONTHS.iter().position(|m| *m == &cap[2])? as u32 + 1;
+        let year: i32 = cap[3].parse().ok()?;
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    /// The page listing all the cycles of a series
+    pub fn cycles_url(&self, series: Series) -> String {
+        let page = match series {
+            Series::Pr => "Zyklen",
+            Series::Neo => "Staffeln_(Perry_Rhodan_Neo)",
+            Series::Atlan => "Atlan-Zyklen",
+        };
+        format!("{}/wiki/{page}", self.client.base_url)
+    }
+
+    /// Parse the table of a cycle overview page. Its columns are found from their header, and
+    /// the rows without a complete number range (e.g. a cycle still being planned) are skipped.
+    pub fn parse_cycles(html: &str, series: Series) -> Vec<Cycle> {
+        let row_re = Regex::new(r"(?s)<tr[^>]*>(.*?)</tr>").unwrap();
+        let cell_re = Regex::new(r"(?s)<t[dh][^>]*>(.*?)</t[dh]>").unwrap();
+        let range_re = Regex::new(r"(\d+)\s*[–-]\s*(\d+)").unwrap();
+        let mut columns: Option<(usize, usize, Option<usize>, usize)> = None;
+        let mut result = Vec::new();
+        for row in row_re.captures_iter(html) {
+            let cells: Vec<String> = cell_re.captures_iter(&row[1])
+                .map(|c| Self::text(&c[1]))
+                .collect();
+            match columns {
+                None => {
+                    let find = |names: &[&str]| cells.iter()
+                        .position(|c| names.iter().any(|n| c.starts_with(n)));
+                    if let (Some(number), Some(title), Some(range)) = (find(&["Nr"]),
+                            find(&["Zyklus", "Staffel"]), find(&["Hefte", "Bände", "Romane"])) {
+                        columns = Some((number, title, find(&["Kurztitel", "Kürzel"]), range));
+                    }
+                }
+                Some((number, title, short_title, range)) => {
+                    let (Some(number), Some(german_title), Some(range)) = (
+                        cells.get(number).and_then(|n| n.parse::<i32>().ok()),
+                        cells.get(title),
+                        cells.get(range).and_then(|r| range_re.captures(r))) else {
+                        continue;
+                    };
+                    let short_title = short_title.and_then(|i| cells.get(i))
+                        .filter(|s| ! s.is_empty())
+                        .unwrap_or(german_title);
+                    result.push(Cycle {
+                        series,
+                        number,
+                        german_title: german_title.clone(),
+                        english_title: "".into(),
+                        short_title: short_title.clone(),
+                        start: range[1].parse().unwrap_or(0),
+                        end: range[2].parse().unwrap_or(0),
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    pub fn _summary_url(series: Series, number: u32) -> String {
+        format!("https://www-perrypedia-de.translate.goog/wiki/Quelle:{}{number}\
+        ?_x_tr_sl=auto&_x_tr_tl=en&_x_tr_hl=en&_x_tr_pto=nui", series.perry_pedia_prefix())
     }
 }
 
 #[async_trait]
 impl CoverFinder for PerryPedia {
-    async fn find_cover_url(&self, n: u32) -> Option<String> {
+    async fn find_cover_url(&self, series: Series, n: u32, _size: CoverSize) -> Option<String> {
         let start = Instant::now();
 
-        let number = format!("{n:04}");
-        let re = Regex::new(&format!(".*(/mediawiki.*/PR{number}.jpg)")).unwrap();
-        let url = format!("{HOST}/wiki/Datei:PR{number:04}.jpg");
-        let r = timeout(Duration::from_millis(TIMEOUT_MS), Self::read_url(url)).await;
+        let file = series.perry_pedia_cover(n);
+        let re = Regex::new(&format!(".*(/mediawiki.*/{})", regex::escape(&file))).unwrap();
+        let url = format!("{}/wiki/Datei:{file}", self.client.base_url);
+        let r = self.read_url(url).await;
 
         debug!(target: "perf", "find_cover_url() elapsed={}ms", start.elapsed().as_millis());
 
         let result = match r {
-            Ok(Some(text)) => {
+            Some(text) => {
                 if let Some(cap) = re.captures(&text) {
                     let link = cap.get(1).unwrap().as_str();
-                    let result = format!("{HOST}{link}");
+                    let result = format!("{}{link}", self.client.base_url);
                     Some(result)
                 } else {
                     None
                 }
             }
-            _ => {
-                info!("find_cover_url(): couldn't retrieve cover for {n} in {TIMEOUT_MS} ms");
+            None => {
+                info!("find_cover_url(): couldn't retrieve cover for {series} {n}");
                 None
             }
         };
@@ -90,11 +423,187 @@ impl CoverFinder for PerryPedia {
         result
     }
 
-    async fn find_cover_urls(&self, numbers: Vec<u32>) -> Vec<Option<String>> {
+    async fn find_cover_urls(&self, series: Series, numbers: Vec<u32>, size: CoverSize)
+        -> Vec<Option<String>>
+    {
         let mut tasks = Vec::new();
         for n in numbers {
-            tasks.push(self.find_cover_url(n as u32));
+            tasks.push(self.find_cover_url(series, n, size));
         }
         futures::future::join_all(tasks).await
     }
-}
\ No newline at end of file
+}
+#[async_trait]
+impl BookMetadataFinder for PerryPedia {
+    async fn find_book_metadata(&self, series: Series, n: u32) -> Option<BookMetadata> {
+        let start = Instant::now();
+        let r = self.read_url(self.quelle_url(series, n)).await;
+        debug!(target: "perf", "find_book_metadata() elapsed={}ms", start.elapsed().as_millis());
+
+        match r {
+            Some(text) => { Self::parse_quelle(&text) }
+            None => {
+                info!("find_book_metadata(): couldn't retrieve metadata for {series} {n}");
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CycleFinder for PerryPedia {
+    async fn find_cycles(&self, series: Series) -> Vec<Cycle> {
+        let start = Instant::now();
+        let r = self.read_url(self.cycles_url(series)).await;
+        debug!(target: "perf", "find_cycles() elapsed={}ms", start.elapsed().as_millis());
+
+        match r {
+            Some(text) => { Self::parse_cycles(&text, series) }
+            None => {
+                info!("find_cycles(): couldn't retrieve the cycles of {series}");
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use chrono::NaiveDate;
+    use crate::config::Config;
+    use crate::entities::Series;
+    use crate::perrypedia::{BookMetadata, BookMetadataFinder, CircuitBreaker, PerryPedia};
+
+    /// Answers every page with a 500 for the first `failures` requests, then with the Quelle
+    /// page of PR 3000. Returns its URL and the number of requests it received.
+    async fn mock_perrypedia(failures: u32) -> (String, Arc<AtomicU32>) {
+        let requests = Arc::new(AtomicU32::new(0));
+        let app = Router::new()
+            .route("/wiki/{page}", get(move |State(requests): State<Arc<AtomicU32>>| async move {
+                if requests.fetch_add(1, Ordering::SeqCst) < failures {
+                    Err(StatusCode::INTERNAL_SERVER_ERROR)
+                } else {
+                    Ok(include_str!("../tests/fixtures/perrypedia/quelle_pr3000.html"))
+                }
+            }))
+            .with_state(requests.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    fn perry_pedia(url: String, retries: u32) -> PerryPedia {
+        PerryPedia::new(&Config {
+            perrypedia_url: url,
+            perrypedia_timeout_ms: 1_000,
+            perrypedia_retries: retries,
+            perrypedia_breaker_failures: 2,
+            perrypedia_breaker_cooldown_secs: 60,
+            perrypedia_cache_secs: 60,
+            ..Config::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn retries_then_caches() {
+        let (url, requests) = mock_perrypedia(2).await;
+        let perry_pedia = perry_pedia(url, 2);
+        let metadata = perry_pedia.find_book_metadata(Series::Pr, 3000).await;
+        assert_eq!(metadata.map(|m| m.title), Some("Mythos Erde".into()));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // The page is read again from the cache
+        assert!(perry_pedia.find_book_metadata(Series::Pr, 3000).await.is_some());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn circuit_breaker() {
+        let (url, requests) = mock_perrypedia(u32::MAX).await;
+        let perry_pedia = perry_pedia(url, 0);
+        assert_eq!(perry_pedia.find_book_metadata(Series::Pr, 1).await, None);
+        assert_eq!(perry_pedia.find_book_metadata(Series::Pr, 2).await, None);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // Two failures in a row opened the breaker, PerryPedia isn't asked anymore
+        assert_eq!(perry_pedia.find_book_metadata(Series::Pr, 3).await, None);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn half_open_breaker() {
+        let cooldown = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::default();
+        assert!(! breaker.failure(2, cooldown, now));
+        assert!(breaker.failure(2, cooldown, now));
+        assert!(! breaker.allows(now + Duration::from_secs(59), cooldown));
+
+        // Half open: a single probe goes through
+        let later = now + cooldown;
+        assert!(breaker.allows(later, cooldown));
+        assert!(! breaker.allows(later, cooldown));
+        assert!(! breaker.allows(later + Duration::from_secs(30), cooldown));
+
+        // It failed, the breaker opens again for a whole cooldown
+        assert!(breaker.failure(2, cooldown, later + Duration::from_secs(1)));
+        assert!(! breaker.allows(later + cooldown, cooldown));
+        let probe = later + cooldown + Duration::from_secs(1);
+        assert!(breaker.allows(probe, cooldown));
+
+        // It succeeded, everything goes through
+        breaker.success();
+        assert!(breaker.allows(probe, cooldown));
+        assert!(breaker.allows(probe, cooldown));
+    }
+
+    #[test]
+    fn parse_quelle_recent_heft() {
+        let html = include_str!("../tests/fixtures/perrypedia/quelle_pr3000.html");
+        assert_eq!(PerryPedia::parse_quelle(html), Some(BookMetadata {
+            title: "Mythos Erde".into(),
+            author: "Andreas Eschbach".into(),
+            published: NaiveDate::from_ymd_opt(2019, 2, 15),
+            cycle: Some("Mythos".into()),
+        }));
+    }
+
+    #[test]
+    fn parse_quelle_first_heft() {
+        let html = include_str!("../tests/fixtures/perrypedia/quelle_pr1.html");
+        assert_eq!(PerryPedia::parse_quelle(html), Some(BookMetadata {
+            title: "Unternehmen Stardust".into(),
+            author: "K. H. Scheer".into(),
+            published: NaiveDate::from_ymd_opt(1961, 9, 8),
+            cycle: Some("Die Dritte Macht".into()),
+        }));
+    }
+
+    #[test]
+    fn parse_quelle_missing_page() {
+        let html = include_str!("../tests/fixtures/perrypedia/quelle_missing.html");
+        assert_eq!(PerryPedia::parse_quelle(html), None);
+    }
+
+    #[test]
+    fn parse_cycles() {
+        let html = include_str!("../tests/fixtures/perrypedia/zyklen.html");
+        let cycles = PerryPedia::parse_cycles(html, Series::Pr);
+        let summary: Vec<(i32, &str, &str, i32, i32)> = cycles.iter()
+            .map(|c| (c.number, c.german_title.as_str(), c.short_title.as_str(), c.start, c.end))
+            .collect();
+        assert_eq!(summary, vec![
+            (1, "Die Dritte Macht", "Dritte Macht", 1, 49),
+            (2, "Atlan und Arkon", "Atlan und Arkon", 50, 99),
+            (3, "Die Posbis", "Posbis", 100, 149),
+        ]);
+    }
+}
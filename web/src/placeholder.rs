@@ -0,0 +1,172 @@
+use image::{Rgb, RgbImage};
+use crate::entities::{CoverSize, Series};
+
+const BACKGROUND: Rgb<u8> = Rgb([224, 224, 224]);
+const FRAME: Rgb<u8> = Rgb([160, 160, 160]);
+const TEXT: Rgb<u8> = Rgb([64, 64, 64]);
+const FRAME_WIDTH: u32 = 16;
+/// The font is 5x7 pixels, each glyph followed by one pixel of spacing
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const ADVANCE: u32 = GLYPH_WIDTH + 1;
+/// Each pixel of the font is a square of that many pixels
+const NUMBER_SCALE: u32 = 24;
+const TEXT_SCALE: u32 = 8;
+const SMALL_SCALE: u32 = 5;
+/// How many lines of the cycle name fit between the number and the bottom line
+const MAX_CYCLE_LINES: usize = 4;
+
+/// The cover shown when no provider has one, drawn at the full size: the series, the number of
+/// the book and its cycle, so that it's still obvious in a list of thumbnails which book it is
+pub fn draw_placeholder(series: Series, number: u32, cycle: Option<&str>) -> RgbImage {
+    let (width, height) = CoverSize::Full.bounds();
+    let mut image = RgbImage::from_fn(width, height, |x, y| {
+        let w = FRAME_WIDTH;
+        if x < w || y < w || x >= width - w || y >= height - w { FRAME } else { BACKGROUND }
+    });
+
+    draw_centered(&mut image, &normalize(series.name()), 120, TEXT_SCALE);
+    draw_centered(&mut image, &number.to_string(), 380, NUMBER_SCALE);
+    if let Some(cycle) = cycle {
+        let max_chars = ((width - 4 * FRAME_WIDTH) / (ADVANCE * TEXT_SCALE)) as usize;
+        for (i, line) in wrap(&normalize(cycle), max_chars).iter().take(MAX_CYCLE_LINES).enumerate() {
+            draw_centered(&mut image, line, 680 + i as u32 * 10 * TEXT_SCALE, TEXT_SCALE);
+        }
+    }
+    draw_centered(&mut image, "NO COVER YET", height - 120, SMALL_SCALE);
+
+    image
+}
+
+/// The font only has capitals, digits and some punctuation
+fn normalize(text: &str) -> String {
+    text.to_uppercase()
+        .replace('Ä', "AE").replace('Ö', "OE").replace('Ü', "UE").replace('ß', "SS")
+        .replace(['É', 'È', 'Ê'], "E").replace(['À', 'Â'], "A")
+}
+
+/// Lines of at most `max_chars`, cut between words unless a word is longer than that
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if ! line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_chars {
+            result.push(line);
+            line = String::new();
+        }
+        if ! line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+        while line.chars().count() > max_chars {
+            let rest: String = line.chars().skip(max_chars).collect();
+            result.push(line.chars().take(max_chars).collect());
+            line = rest;
+        }
+    }
+    if ! line.is_empty() {
+        result.push(line);
+    }
+    result
+}
+
+fn draw_centered(image: &mut RgbImage, text: &str, y: u32, scale: u32) {
+    let width = (text.chars().count() as u32 * ADVANCE).saturating_sub(1) * scale;
+    let x = image.width().saturating_sub(width) / 2;
+    for (i, c) in text.chars().enumerate() {
+        draw_glyph(image, glyph(c), x + i as u32 * ADVANCE * scale, y, scale);
+    }
+}
+
+fn draw_glyph(image: &mut RgbImage, rows: [u8; 7], x: u32, y: u32, scale: u32) {
+    for (row, bits) in rows.iter().enumerate() {
+        for column in 0..GLYPH_WIDTH {
+            if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                continue;
+            }
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let (px, py) = (x + column * scale + dx, y + row as u32 * scale + dy);
+                    if px < image.width() && py < image.height() {
+                        image.put_pixel(px, py, TEXT);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The rows of a 5x7 glyph, the leftmost pixel being the highest of the 5 bits
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::{CoverSize, Series};
+    use crate::placeholder::{draw_placeholder, normalize, wrap};
+
+    #[test]
+    fn cycle_names_are_wrapped_between_words() {
+        assert_eq!(wrap(&normalize("Die Dritte Macht"), 13), vec!["DIE DRITTE", "MACHT"]);
+        assert_eq!(wrap(&normalize("Gänger des Netzes"), 13), vec!["GAENGER DES", "NETZES"]);
+        assert_eq!(wrap("ABCDEFGHIJKLMNOP", 13), vec!["ABCDEFGHIJKLM", "NOP"]);
+    }
+
+    #[test]
+    fn placeholders_differ_by_book() {
+        let first = draw_placeholder(Series::Pr, 1, Some("Die Dritte Macht"));
+        assert_eq!(first.dimensions(), CoverSize::Full.bounds());
+        assert_ne!(first, draw_placeholder(Series::Pr, 2, Some("Die Dritte Macht")));
+        assert_ne!(first, draw_placeholder(Series::Pr, 1, None));
+    }
+}
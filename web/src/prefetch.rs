@@ -0,0 +1,117 @@
+use std::future::ready;
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use tokio::time::sleep;
+use tracing::{info, warn};
+use crate::covers::prefetch_cover;
+use crate::entities::MissingCover;
+use crate::PerryState;
+
+/// How many books the job looks at each time
+const BATCH_SIZE: i64 = 50;
+/// Between two batches that went fine
+const BATCH_PAUSE: Duration = Duration::from_secs(5);
+/// When no cover is missing
+const IDLE_PAUSE: Duration = Duration::from_secs(60 * 60);
+/// After a batch where every download failed, PerryPedia is probably down: wait this long,
+/// doubling each time up to MAX_BACKOFF
+const MIN_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// What the prefetch job has done since the server started
+#[derive(Clone, Debug, Default)]
+pub struct PrefetchProgress {
+    pub running: bool,
+    /// Start of the current batch, or of the last one
+    pub batch_started: Option<DateTime<Utc>>,
+    pub batch_size: usize,
+    pub fetched: u64,
+    pub failed: u64,
+    pub last_error: Option<String>,
+    /// When the job looks for missing covers again, `None` while a batch is running
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+pub struct CoverPrefetch {
+    progress: Mutex<PrefetchProgress>,
+}
+
+impl CoverPrefetch {
+    pub fn progress(&self) -> PrefetchProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut PrefetchProgress)) {
+        f(&mut self.progress.lock().unwrap());
+    }
+}
+
+/// Download in the background the covers nobody asked for yet, so that the first visitor of a
+/// cycle doesn't wait for PerryPedia
+pub fn start_cover_prefetch(state: PerryState) {
+    if ! state.config.prefetch_covers {
+        info!("Cover prefetch is disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let prefetch = state.cover_prefetch.clone();
+        let concurrency = state.config.prefetch_concurrency.max(1);
+        prefetch.update(|p| p.running = true);
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            let missing = state.db.find_missing_covers(BATCH_SIZE).await;
+            let pause = if missing.is_empty() {
+                IDLE_PAUSE
+            } else {
+                info!("Prefetching {} covers", missing.len());
+                prefetch.update(|p| {
+                    p.batch_started = Some(Utc::now());
+                    p.batch_size = missing.len();
+                    p.next_run = None;
+                });
+                let successes = stream::iter(missing)
+                    .map(|m| prefetch_one(state.clone(), m))
+                    .buffer_unordered(concurrency)
+                    .filter(|success| ready(*success))
+                    .count()
+                    .await;
+                if successes == 0 {
+                    let pause = backoff;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    warn!("No cover could be prefetched, waiting {} seconds", pause.as_secs());
+                    pause
+                } else {
+                    backoff = MIN_BACKOFF;
+                    BATCH_PAUSE
+                }
+            };
+            prefetch.update(|p| p.next_run = Some(Utc::now() + pause));
+            sleep(pause).await;
+        }
+    });
+}
+
+async fn prefetch_one(state: PerryState, missing: MissingCover) -> bool {
+    let (series, number) = (missing.series, missing.number as u32);
+    match prefetch_cover(&state, &missing).await {
+        Ok(()) => {
+            info!("Prefetched cover {series} {number}");
+            let _ = state.db.delete_cover_fetch_failure(series, number).await;
+            state.cover_prefetch.update(|p| p.fetched += 1);
+            true
+        }
+        Err(e) => {
+            warn!("Couldn't prefetch cover {series} {number}: {e}");
+            let _ = state.db.record_cover_fetch_failure(series, number, e.to_string(), e.is_permanent()).await;
+            state.cover_prefetch.update(|p| {
+                p.failed += 1;
+                p.last_error = Some(format!("{series} {number}: {e}"));
+            });
+            false
+        }
+    }
+}
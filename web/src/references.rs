@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use regex::{Captures, Regex};
+use tracing::info;
+use crate::db::Db;
+use crate::entities::Series;
+use crate::errors::{PrResult, PrResultBuilder};
+use crate::url::Urls;
+use crate::{CookieManager, PerryState};
+
+/// "#1234", "PR 2991", "PR2991"
+const REFERENCE_PATTERN: &str = r"(?:#|\bPR ?)(\d{1,4})\b";
+
+/// Numeric HTML entities such as `&#8217;` look like references but aren't
+fn is_entity(segment: &str, cap: &Captures) -> bool {
+    segment[..cap.get(0).unwrap().start()].ends_with('&')
+}
+
+/// The book numbers mentioned in a summary, in order of appearance, without duplicates
+pub fn find_references(text: &str) -> Vec<u32> {
+    let re = Regex::new(REFERENCE_PATTERN).unwrap();
+    let mut result = Vec::new();
+    for (segment, is_text) in segments(text) {
+        if is_text {
+            for cap in re.captures_iter(segment).filter(|cap| ! is_entity(segment, cap)) {
+                if let Ok(n) = cap[1].parse::<u32>() {
+                    if n > 0 && ! result.contains(&n) {
+                        result.push(n);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Turn the references of a summary into links to the books of the same series. `host` is
+/// prepended to the URL's (empty for the web site, absolute for emails) and references to books
+/// that don't have a summary yet are flagged with the `ref-missing` class.
+pub fn link_references(series: Series, text: &str, host: &str, existing: &HashSet<u32>) -> String {
+    let re = Regex::new(REFERENCE_PATTERN).unwrap();
+    let mut result = String::with_capacity(text.len());
+    for (segment, is_text) in segments(text) {
+        if is_text {
+            result.push_str(&re.replace_all(segment, |cap: &Captures| {
+                if is_entity(segment, cap) {
+                    return cap[0].to_string();
+                }
+                let number = cap[1].parse::<u32>().unwrap_or(0);
+                let href = format!("{host}{}", Urls::summary(series, number as i32));
+                if existing.contains(&number) {
+                    format!("<a class=\"ref\" href=\"{href}\">{}</a>", &cap[0])
+                } else {
+                    format!("<a class=\"ref ref-missing\" href=\"{href}\" \
+                        title=\"No summary yet\">{}</a>", &cap[0])
+                }
+            }));
+        } else {
+            result.push_str(segment);
+        }
+    }
+    result
+}
+
+/// Split HTML into (segment, is_text). Tags and anything inside an existing `<a>` element
+/// are not text, so they're never rewritten.
+fn segments(html: &str) -> Vec<(&str, bool)> {
+    let tag = Regex::new(r"<[^>]*>").unwrap();
+    let mut result = Vec::new();
+    let mut in_anchor = false;
+    let mut last = 0;
+    for m in tag.find_iter(html) {
+        if m.start() > last {
+            result.push((&html[last..m.start()], ! in_anchor));
+        }
+        let t = m.as_str().to_lowercase();
+        if t.starts_with("<a ") || t == "<a>" {
+            in_anchor = true;
+        } else if t == "</a>" || t.starts_with("</a ") {
+            in_anchor = false;
+        }
+        result.push((m.as_str(), false));
+        last = m.end();
+    }
+    if last < html.len() {
+        result.push((&html[last..], ! in_anchor));
+    }
+    result
+}
+
+/// Link the references of a summary, looking up which of them already have a summary
+pub async fn render_summary_text(db: &Arc<Box<dyn Db>>, series: Series, text: &str, host: &str) -> String {
+    let references = find_references(text);
+    if references.is_empty() {
+        text.to_string()
+    } else {
+        let existing: HashSet<u32> = db.find_summary_numbers(series, references).await.into_iter().collect();
+        link_references(series, text, host, &existing)
+    }
+}
+
+/// Recompute the backlinks index from the text of every summary
+pub async fn rebuild_references_logic<T>(state: &PerryState, cookie_manager: impl CookieManager<T>)
+    -> PrResult
+{
+    match cookie_manager.find_user(state.db.clone()).await {
+        Some(u) if u.is_admin() => {
+            let summaries = state.db.fetch_all_summaries().await;
+            info!("Rebuilding the references of {} summaries", summaries.len());
+            for summary in summaries {
+                state.db.update_references(summary.series, summary.number as u32,
+                        find_references(&summary.summary))
+                    .await?;
+            }
+            PrResultBuilder::redirect(Urls::root())
+        }
+        _ => {
+            PrResultBuilder::root()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::entities::Series;
+    use crate::references::{find_references, link_references, segments};
+
+    #[test]
+    fn references_are_found_once_in_order() {
+        assert_eq!(find_references("See #12, PR 2991 and PR2991, then #12 again."), vec![12, 2991]);
+        assert_eq!(find_references("<p>#7</p><b>PR 8</b>"), vec![7, 8]);
+        // Not a book
+        assert!(find_references("#0 and PR 0").is_empty());
+        // Too long, and a word that happens to end with PR
+        assert!(find_references("#12345, SPR 12").is_empty());
+    }
+
+    #[test]
+    fn entities_and_links_are_left_alone() {
+        assert!(find_references("Perry&#8217;s ship &#160;").is_empty());
+        assert!(find_references("<a href=\"/summaries/3\">#3</a>").is_empty());
+        assert_eq!(find_references("<A HREF=\"x\">PR 3</A> then #4"), vec![4]);
+        assert!(find_references("<a href=\"x\"><abbr>PR</abbr> #5</a>").is_empty());
+    }
+
+    #[test]
+    fn links() {
+        let existing: HashSet<u32> = [12].into_iter().collect();
+        assert_eq!(link_references(Series::Pr, "#12 &#8217; PR 13", "", &existing),
+            "<a class=\"ref\" href=\"/summaries/12\">#12</a> &#8217; \
+            <a class=\"ref ref-missing\" href=\"/summaries/13\" title=\"No summary yet\">PR 13</a>");
+        assert_eq!(link_references(Series::Pr, "PR2991", "https://perryrhodan.us", &existing),
+            "<a class=\"ref ref-missing\" href=\"https://perryrhodan.us/summaries/2991\" \
+            title=\"No summary yet\">PR2991</a>");
+        let linked = "<a href=\"/summaries/12\">#12</a>";
+        assert_eq!(link_references(Series::Pr, linked, "", &existing), linked);
+        assert_eq!(link_references(Series::Neo, "#12", "", &existing),
+            "<a class=\"ref\" href=\"/neo/summaries/12\">#12</a>");
+    }
+
+    #[test]
+    fn html_segments() {
+        assert_eq!(segments("a <b>c</b> <a href=\"x\">d</a> e"), vec![
+            ("a ", true), ("<b>", false), ("c", true), ("</b>", false), (" ", true),
+            ("<a href=\"x\">", false), ("d", false), ("</a>", false), (" e", true),
+        ]);
+        // "<abbr>" isn't an anchor
+        assert_eq!(segments("<abbr>#1</abbr>"), vec![("<abbr>", false), ("#1", true), ("</abbr>", false)]);
+    }
+}
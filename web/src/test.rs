@@ -1,13 +1,16 @@
 
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::config::Config;
     use crate::db::{Db};
     use crate::email::Email;
     use crate::entities::{Book, Cycle, Summary};
     use crate::errors::PrResult;
-    use crate::perrypedia::CoverFinder;
+    use crate::perrypedia::{BookMetadataFinder, CoverFinder, CycleFinder};
+    use crate::prefetch::CoverPrefetch;
+    use crate::cover_store::PostgresCoverStore;
+    use crate::cover_providers::CoverProviders;
     use crate::{init_logging, PerryState};
     use async_trait::async_trait;
     use figment::providers::{Format, Json};
@@ -69,15 +72,23 @@ mod tests {
 
     struct CoverFinderTest;
     impl CoverFinder for CoverFinderTest {}
+    impl BookMetadataFinder for CoverFinderTest {}
+    impl CycleFinder for CoverFinderTest {}
 
-    async fn create_state(db: Box<dyn Db>) -> PerryState {
+    pub(crate) async fn create_state(db: Box<dyn Db>) -> PerryState {
         let config = Config::default();
+        let db: Arc<Box<dyn Db>> = Arc::new(db);
         PerryState {
             app_name: "Perry Test".into(),
             config: config.clone(),
-            db: Arc::new(db),
+            db: db.clone(),
             email_service: Arc::new(Email::create_email_service(&config).await),
             cover_finder: Arc::new(Box::new(CoverFinderTest{})),
+            metadata_finder: Arc::new(Box::new(CoverFinderTest{})),
+            cycle_finder: Arc::new(Box::new(CoverFinderTest{})),
+            cover_prefetch: Arc::new(CoverPrefetch::default()),
+            cover_store: Arc::new(Box::new(PostgresCoverStore { db })),
+            cover_providers: Arc::new(CoverProviders::new(Vec::new())),
         }
     }
 
@@ -1,12 +1,42 @@
+use crate::entities::{CoverSize, Series};
+
 pub struct Urls;
 
 pub const CYCLES: &str = &"cycles";
 pub const SUMMARIES: &str = &"summaries";
 
 impl Urls {
-    pub fn cycles(number: i32) -> String {
-        format!("/{CYCLES}/{number}")
+    /// The main series keeps the URL's it always had, the other ones are prefixed with their code
+    pub fn series(series: Series) -> String {
+        match series {
+            Series::Pr => "".into(),
+            _ => format!("/{}", series.code().to_lowercase()),
+        }
+    }
+    pub fn cycles(series: Series, number: i32) -> String {
+        format!("{}/{CYCLES}/{number}", Self::series(series))
+    }
+    pub fn cycle_covers(series: Series, number: i32) -> String {
+        format!("{}/covers", Self::cycles(series, number))
+    }
+    pub fn summary(series: Series, number: i32) -> String {
+        format!("{}/{SUMMARIES}/{number}", Self::series(series))
     }
-    pub fn summary(number: i32) -> String { format!("/{SUMMARIES}/{number}") }
+    pub fn book(series: Series, number: i32) -> String {
+        format!("{}/books/{number}", Self::series(series))
+    }
+    pub fn cover(series: Series, number: i32) -> String {
+        format!("{}/covers/{number:04}", Self::series(series))
+    }
+    pub fn cover_sized(series: Series, number: i32, size: CoverSize) -> String {
+        format!("{}?size={}", Self::cover(series, number), size.name())
+    }
+    pub fn cover_report() -> String { "/covers/report".into() }
     pub fn root() -> String { "/".into() }
+    pub fn verify_comment(token: &str) -> String { format!("/comments/verify/{token}") }
+    pub fn comments_moderation() -> String { "/comments/moderation".into() }
+    pub fn character(slug: &str) -> String { format!("/characters/{slug}") }
+    pub fn summary_entities(series: Series, number: i32) -> String {
+        format!("{}/entities", Self::summary(series, number))
+    }
 }
@@ -5,7 +5,13 @@ function httpGet(url, xmlHttp = new XMLHttpRequest()) {
 }
 
 function sendEmailMailingList(number) {
-    httpGet("/api/sendEmail/" + number);
+    httpGet("/api" + seriesPrefix() + "/sendEmail/" + number);
+}
+
+/** "/neo" on "/neo/summaries/12", empty for the main series */
+function seriesPrefix() {
+    const paths = new URL(window.location).pathname.split("/");
+    return paths.length > 3 ? "/" + paths[1] : "";
 }
 
 function numberFromPath() {
@@ -19,45 +25,16 @@ function openForm() {
     document.getElementById("login-modal").style.display = "block";
 }
 
+/** Spoiler-safe mode: unblur an element the reader clicked on */
+function reveal(element) {
+    element.classList.remove("spoiler");
+    element.onclick = null;
+}
+
 function closeForm() {
     document.getElementById("login-modal").style.display = "none";
 }
 
-const createApp = function (htmlUrl, apiUrl, text) {
-    return {
-        el: '#app',
-        data: {
-            currentNumber: 0,
-            result: null
-        },
-        created: function () {
-            this.currentNumber = numberFromPath();
-            this.result = this.fetch();
-        },
-        methods: {
-            fetch: function () {
-                const result = this.find(this.currentNumber);
-                window.history.pushState(result,
-                    text + " " + this.currentNumber, htmlUrl + "/" + this.currentNumber);
-                return result;
-            },
-            find: function (number) {
-                return JSON.parse(httpGet(apiUrl + "/" + number));
-            },
-            next: function () {
-                this.currentNumber++;
-                this.result = this.fetch();
-            },
-            previous: function () {
-                if (this.currentNumber > 1) {
-                    this.currentNumber--;
-                    this.result = this.fetch();
-                }
-            }
-        }
-    };
-};
-
 function submitSummary() {
     $("#editSummaryForm").submit( function(eventObj) {
         $('<input />')
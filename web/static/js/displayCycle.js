@@ -1 +0,0 @@
-var app = new Vue(createApp("/cycles", "/api/cycles", "Cycle"));
@@ -1 +1,35 @@
-var app = new Vue(createApp("/summaries", "/api/summaries", "Issue"));
+// The summary itself is rendered on the server, only comments and the reading status
+// are loaded from the API, at the URL's given by the page
+const readingElement = document.getElementById("reading");
+const commentsElement = document.getElementById("comments");
+
+if (readingElement) {
+    new Vue({
+        el: '#reading',
+        data: {
+            reading: JSON.parse(httpGet(readingElement.dataset.url))
+        }
+    });
+}
+
+if (commentsElement) {
+    new Vue({
+        el: '#comments',
+        data: {
+            comments: JSON.parse(httpGet(commentsElement.dataset.url)),
+            replyTo: null
+        }
+    });
+}
+
+/**
+ * Spoiler-safe mode: show the title and the summary the reader chose to see anyway. The later
+ * summaries referencing this one stay hidden until revealed one by one.
+ */
+function revealSummary() {
+    document.querySelectorAll(".spoiler:not(.backlink)").forEach(reveal);
+    const warning = document.getElementById("spoiler-warning");
+    if (warning) {
+        warning.remove();
+    }
+}